@@ -1,54 +1,178 @@
+mod per_direction;
+
+use std::time::{Duration, Instant};
+
 use criterion::{criterion_group, criterion_main, Criterion};
-use game2048_plonky2::game2048::{Game2048Circuit, F};
+use game2048_plonky2::game2048::{
+    Board, CircuitProfile, CircuitStats, Direction, Game2048Circuit, Game2048Engine, Game2048Error, Game2048Prover,
+    Game2048Targets, PlayerIdentity, C, D, F,
+};
 use plonky2::field::types::Field;
-use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
 use plonky2::plonk::config::PoseidonGoldilocksConfig;
 
+fn identity() -> PlayerIdentity {
+    PlayerIdentity::new(&[F::from_canonical_u64(42)], 1)
+}
+
 fn game2048_generate_proof(c: &mut Criterion) {
-    let before_board: Vec<F> = vec![
-        F::from_canonical_u32(2), F::from_canonical_u32(2), F::ZERO,                    F::from_canonical_u32(4),
-        F::ZERO,                  F::ZERO,                  F::from_canonical_u32(4),   F::ZERO,
-        F::from_canonical_u32(2), F::ZERO,                  F::ZERO,                    F::from_canonical_u32(2),
-        F::ZERO,                  F::from_canonical_u32(4), F::ZERO,                    F::ZERO,
-    ];
-    
-    let after_board: Vec<F> = vec![
-        F::from_canonical_u32(4),   F::from_canonical_u32(2),   F::from_canonical_u32(4),   F::from_canonical_u32(4),
-        F::ZERO,                    F::from_canonical_u32(4),   F::ZERO,                    F::from_canonical_u32(2),
-        F::ZERO,                    F::ZERO,                    F::ZERO,                    F::ZERO,
-        F::ZERO,                    F::ZERO,                    F::ZERO,                    F::ZERO,
+    let before_board: Board = [
+        2, 2, 0, 4, //
+        0, 0, 4, 0, //
+        2, 0, 0, 2, //
+        0, 4, 0, 0, //
     ];
 
-    let direction = F::from_canonical_u32(0); // Direction: "up"
+    let after_board: Board = [
+        4, 2, 4, 4, //
+        0, 4, 0, 2, //
+        0, 0, 0, 0, //
+        0, 0, 0, 0, //
+    ];
 
     c.bench_function("game2048_prove_and_verify", |b| {
         b.iter(|| {
-            let (builder, targets) = Game2048Circuit::build_circuit();
+            let prover = Game2048Prover::new();
+            let verified = prover
+                .prove(&before_board, &after_board, Direction::Up, &identity())
+                .and_then(|proof| prover.verifier().verify(&proof));
+            if let Err(err) = verified {
+                panic!("Proof verification failed: {err}");
+            }
+        });
+    });
+}
 
-            let circuit = builder.build::<PoseidonGoldilocksConfig>();
-            let mut pw = PartialWitness::<F>::new();
-            let before_board_targets = &targets[0..16];
-            let after_board_targets = &targets[16..32];
-            let direction_target = targets[32];
+type BuildFn = fn() -> (CircuitBuilder<F, D>, Game2048Targets);
 
-            // Assign values for before_board
-            for (i, &target) in before_board_targets.iter().enumerate() {
-                pw.set_target(target, before_board[i]).unwrap();
-            }
+/// Compare the arithmetic merge against the lookup table merge on the same move
+fn game2048_merge_strategies(c: &mut Criterion) {
+    let before_board: Board = [2, 2, 0, 4, 0, 0, 4, 0, 2, 0, 0, 2, 0, 4, 0, 0];
+    let after_board: Board = [4, 4, 0, 0, 4, 0, 0, 0, 4, 0, 0, 0, 4, 0, 0, 0];
 
-            // Assign values for after_board
-            for (i, &target) in after_board_targets.iter().enumerate() {
-                pw.set_target(target, after_board[i]).unwrap();
-            }
+    let strategies: [(&str, BuildFn); 2] = [
+        ("arithmetic", Game2048Circuit::build_circuit),
+        ("lookup", Game2048Circuit::build_lookup_circuit),
+    ];
 
-            // Assign the direction
-            pw.set_target(direction_target, direction).unwrap();
+    let mut group = c.benchmark_group("game2048_merge_strategy");
+    for (name, build) in strategies {
+        let (builder, targets) = build();
+        // Lookup gates are only added during build, so this counts the merge logic alone
+        let num_gates = builder.num_gates();
+        let circuit = builder.build::<C>();
+        println!(
+            "{name}: {num_gates} gates before lookups, degree_bits {}",
+            circuit.common.degree_bits()
+        );
 
-            let proof = circuit.prove(pw);
-            assert!(circuit.verify(proof.unwrap()).is_ok(), "Proof verification failed");
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut pw = PartialWitness::<F>::new();
+                let verified = targets
+                    .set_witness(&mut pw, &before_board, &after_board, Direction::Left)
+                    .and_then(|()| circuit.prove(pw).map_err(Game2048Error::ProofFailure))
+                    .and_then(|proof| circuit.verify(proof).map_err(Game2048Error::ProofFailure));
+                if let Err(err) = verified {
+                    panic!("Proof verification failed: {err}");
+                }
+            });
         });
-    });
+    }
+    group.finish();
+}
+
+/// Compare the oriented move circuit against the old one with a masked merge per direction
+fn game2048_orientation(c: &mut Criterion) {
+    let before_board: Board = [2, 2, 0, 4, 0, 0, 4, 0, 2, 0, 0, 2, 0, 4, 0, 0];
+    let after_board = Game2048Engine::apply_move(&before_board, Direction::Left);
+
+    let circuits: [(&str, BuildFn); 2] = [
+        ("per-direction", per_direction::build_per_direction_circuit),
+        ("oriented", Game2048Circuit::build_circuit),
+    ];
+
+    // Rows other than padding, as counted by CircuitStats once the circuit is built
+    println!("{:<16}{:>8}{:>14}{:>14}", "circuit", "gates", "degree_bits", "proof size");
+    for (name, build) in circuits {
+        let stats = CircuitStats::measure(build()).unwrap();
+        println!("{:<16}{:>8}{:>14}{:>8} bytes", name, stats.num_gates, stats.degree_bits, stats.proof_size);
+    }
+
+    let mut group = c.benchmark_group("game2048_orientation");
+    group.sample_size(10);
+    for (name, build) in circuits {
+        let (builder, targets) = build();
+        let circuit = builder.build::<C>();
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut pw = PartialWitness::<F>::new();
+                targets.set_witness(&mut pw, &before_board, &after_board, Direction::Left).unwrap();
+                let proof = circuit.prove(pw).unwrap();
+                circuit.verify(proof).unwrap();
+            });
+        });
+    }
+    group.finish();
+}
+
+/// Prove and verify times and proof size of the move circuit under each profile
+fn game2048_profiles(c: &mut Criterion) {
+    const RUNS: u32 = 5;
+    let before_board: Board = [2, 2, 0, 4, 0, 0, 4, 0, 2, 0, 0, 2, 0, 4, 0, 0];
+    let after_board = Game2048Engine::apply_move(&before_board, Direction::Left);
+
+    let provers: Vec<_> = CircuitProfile::ALL
+        .map(|profile| (profile, Game2048Prover::<PoseidonGoldilocksConfig>::with_profile(profile)))
+        .into_iter()
+        .collect();
+
+    // Quick table of averages over a few runs, followed by the criterion measurements
+    println!("{:<16}{:>12}{:>12}{:>14}", "profile", "prove", "verify", "proof size");
+    for (profile, prover) in &provers {
+        let verifier = prover.verifier();
+        let (mut prove_time, mut verify_time) = (Duration::ZERO, Duration::ZERO);
+        let mut proof_size = 0;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            let proof = prover.prove(&before_board, &after_board, Direction::Left, &identity()).unwrap();
+            prove_time += start.elapsed();
+
+            let start = Instant::now();
+            verifier.verify(&proof).unwrap();
+            verify_time += start.elapsed();
+            proof_size = proof.to_bytes().len();
+        }
+        println!(
+            "{:<16}{:>12.2?}{:>12.2?}{:>8} bytes",
+            profile.name(),
+            prove_time / RUNS,
+            verify_time / RUNS,
+            proof_size
+        );
+    }
+
+    let mut group = c.benchmark_group("game2048_profile");
+    group.sample_size(10);
+    for (profile, prover) in &provers {
+        let verifier = prover.verifier();
+        let proof = prover.prove(&before_board, &after_board, Direction::Left, &identity()).unwrap();
+        group.bench_function(format!("prove/{}", profile.name()), |b| {
+            b.iter(|| prover.prove(&before_board, &after_board, Direction::Left, &identity()).unwrap());
+        });
+        group.bench_function(format!("verify/{}", profile.name()), |b| {
+            b.iter(|| verifier.verify(&proof).unwrap());
+        });
+    }
+    group.finish();
 }
 
-criterion_group!(game2048_benchmark, game2048_generate_proof);
-criterion_main!(game2048_benchmark);
\ No newline at end of file
+criterion_group!(
+    game2048_benchmark,
+    game2048_generate_proof,
+    game2048_merge_strategies,
+    game2048_orientation,
+    game2048_profiles
+);
+criterion_main!(game2048_benchmark);
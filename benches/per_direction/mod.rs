@@ -0,0 +1,48 @@
+//! The move circuit as it was before boards were oriented by direction, kept to
+//! measure what the orientation saves.
+//!
+//! Each direction merges its own four lines, and a flag per direction masks which
+//! merges the after board must match.
+
+use game2048_plonky2::game2048::{
+    CircuitProfile, ClassicRules, Direction, Game2048Circuit, Game2048Engine, Game2048Targets, RuleSet, D, F,
+};
+use plonky2::field::types::Field;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+/// Build the four-direction move circuit, with the same public inputs and tile checks
+/// as `Game2048Circuit::build_circuit`
+pub fn build_per_direction_circuit() -> (CircuitBuilder<F, D>, Game2048Targets) {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitProfile::default().config());
+
+    let before_board = builder.add_virtual_target_arr::<16>();
+    let after_board = builder.add_virtual_target_arr::<16>();
+    let direction = builder.add_virtual_target();
+    builder.register_public_inputs(&before_board);
+    builder.register_public_inputs(&after_board);
+    builder.register_public_input(direction);
+
+    for &tile in before_board.iter().chain(&after_board) {
+        ClassicRules.add_tile_check(&mut builder, tile);
+    }
+
+    let flags = Direction::ALL.map(|candidate| {
+        let candidate = builder.constant(F::from_canonical_u64(candidate.as_u64()));
+        builder.is_equal(direction, candidate)
+    });
+    let flag_sum = builder.add_many(flags.map(|flag| flag.target));
+    builder.assert_one(flag_sum);
+
+    for (candidate, flag) in Direction::ALL.into_iter().zip(flags) {
+        for line in Game2048Engine::lines(candidate) {
+            let [a, b, c, d] = line.map(|cell| before_board[cell]);
+            let merged = Game2048Circuit::merge_2048_row(&mut builder, a, b, c, d);
+            for (merged_tile, cell) in merged.into_iter().zip(line) {
+                let expected = builder._if(flag, merged_tile, after_board[cell]);
+                builder.connect(expected, after_board[cell]);
+            }
+        }
+    }
+
+    (builder, Game2048Targets { before_board, after_board, direction })
+}
@@ -0,0 +1,378 @@
+//! Salted board commitments, and circuits that prove moves between committed boards.
+//!
+//! A commitment is the Poseidon hash of the 16 tiles followed by a 4-element salt,
+//! so boards with few possible values can't be recovered by hashing every candidate,
+//! by the player identity, so a commitment only stands for one player's session, and
+//! by the move index, so it stands for one point of the game. With the zero-knowledge
+//! profile, a proof reveals nothing but the two commitments, the identity, the move
+//! indices, the first and last timestamps, the server's seed commitment and the player
+//! entropy.
+//!
+//! Segments are traces of the seeded game: move n is followed by spawn number
+//! OPENING_TILES + n, and a segment starting at move 0 starts from the opening board
+//! derived from the seed. A game proven in segments is free of undos when every later
+//! segment starts from the commitment its predecessor ended on, at the move index it
+//! ended on, with the same seed; see `CommittedTraceProver::verify_chain`, which also
+//! checks the minimum interval between the last move of a segment and the first move
+//! of the next.
+
+use plonky2::field::types::{Field, PrimeField64, Sample};
+use plonky2::hash::hash_types::{HashOut, HashOutTarget};
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{ProverCircuitData, VerifierCircuitData};
+use plonky2::plonk::config::Hasher;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+
+use super::{
+    Board, CircuitProfile, ClassicRules, Game2048Circuit, Game2048Engine, Game2048Error, IdentityTargets,
+    PlayerEntropy, PlayerIdentity, RuleSet, ServerSeed, TimedMove, TraceTargets, C, D, F, IDENTITY_LEN,
+};
+
+/// Random field elements hashed with a board to hide it
+pub type Salt = [F; 4];
+
+impl Game2048Engine {
+    /// Commitment to the board at move move_index: Poseidon(tiles || salt || identity || move_index)
+    pub fn commit_board(board: &Board, salt: &Salt, identity: &PlayerIdentity, move_index: u64) -> HashOut<F> {
+        let inputs: Vec<F> = board
+            .iter()
+            .map(|&tile| F::from_canonical_u64(tile))
+            .chain(salt.iter().copied())
+            .chain(identity.elements())
+            .chain([F::from_canonical_u64(move_index)])
+            .collect();
+        PoseidonHash::hash_no_pad(&inputs)
+    }
+
+    /// A fresh random salt
+    pub fn random_salt() -> Salt {
+        F::rand_array()
+    }
+}
+
+/// Targets of a trace whose initial and final boards are only public as commitments
+#[derive(Clone, Debug)]
+pub struct CommittedTraceTargets {
+    /// The moves, from the initial board at move `trace.start_move`
+    pub trace: TraceTargets,
+    pub initial_salt: [Target; 4],
+    pub final_salt: [Target; 4],
+    /// start_move + num_moves, the final board's move index
+    pub end_move: Target,
+    pub initial_commitment: HashOutTarget,
+    pub final_commitment: HashOutTarget,
+}
+
+impl CommittedTraceTargets {
+    /// Assign the seed, the moves from initial_board, the board at move start_move,
+    /// and both salts, returning every board of the trace
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_witness(
+        &self,
+        pw: &mut PartialWitness<F>,
+        rules: &dyn RuleSet,
+        server_seed: &ServerSeed,
+        player_entropy: &PlayerEntropy,
+        identity: &PlayerIdentity,
+        start_move: u64,
+        initial_board: &Board,
+        moves: &[TimedMove],
+        initial_salt: &Salt,
+        final_salt: &Salt,
+    ) -> Result<Vec<Board>, Game2048Error> {
+        let boards = self.trace.set_segment_witness(
+            pw,
+            rules,
+            server_seed,
+            player_entropy,
+            identity,
+            start_move,
+            initial_board,
+            moves,
+        )?;
+        pw.set_target_arr(&self.initial_salt, initial_salt)
+            .map_err(Game2048Error::WitnessConflict)?;
+        pw.set_target_arr(&self.final_salt, final_salt)
+            .map_err(Game2048Error::WitnessConflict)?;
+        Ok(boards)
+    }
+}
+
+impl Game2048Circuit {
+    /// Build a circuit proving a single move between two committed boards
+    ///
+    /// The direction is private too. Use `CircuitProfile::ZeroKnowledge` so that
+    /// the proof doesn't leak the boards.
+    pub fn build_committed_circuit(profile: CircuitProfile) -> (CircuitBuilder<F, D>, CommittedTraceTargets) {
+        Self::committed_trace_circuit(1, 0, profile, &ClassicRules)
+    }
+
+    /// Build a circuit for num_moves consecutive moves of a seeded game between two
+    /// committed boards, at least min_interval ticks apart
+    ///
+    /// Public inputs are the initial commitment (4), the final commitment (4), the
+    /// player identity (5), the start move index (1), the end move index (1), the
+    /// first and last timestamps (2), the server's seed commitment (4) and the player
+    /// entropy (4).
+    pub fn build_committed_trace_circuit(
+        num_moves: usize,
+        min_interval: u64,
+        profile: CircuitProfile,
+    ) -> Result<(CircuitBuilder<F, D>, CommittedTraceTargets), Game2048Error> {
+        Self::build_committed_trace_circuit_with_rules(num_moves, min_interval, profile, &ClassicRules)
+    }
+
+    /// Build the committed trace circuit for a 2048 variant
+    pub fn build_committed_trace_circuit_with_rules(
+        num_moves: usize,
+        min_interval: u64,
+        profile: CircuitProfile,
+        rules: &dyn RuleSet,
+    ) -> Result<(CircuitBuilder<F, D>, CommittedTraceTargets), Game2048Error> {
+        if num_moves == 0 {
+            return Err(Game2048Error::EmptyTrace);
+        }
+        Ok(Self::committed_trace_circuit(num_moves, min_interval, profile, rules))
+    }
+
+    fn committed_trace_circuit(
+        num_moves: usize,
+        min_interval: u64,
+        profile: CircuitProfile,
+        rules: &dyn RuleSet,
+    ) -> (CircuitBuilder<F, D>, CommittedTraceTargets) {
+        let mut builder = CircuitBuilder::<F, D>::new(profile.config());
+        let trace = Self::add_trace(&mut builder, rules, num_moves, min_interval);
+
+        let initial_salt = builder.add_virtual_target_arr::<4>();
+        let final_salt = builder.add_virtual_target_arr::<4>();
+        let identity = trace.opening.seed.identity;
+        let start_move = trace.start_move;
+        let num_moves_const = builder.constant(F::from_canonical_usize(num_moves));
+        let end_move = builder.add(start_move, num_moves_const);
+
+        let initial_board = &trace.boards[0];
+        let final_board = &trace.boards[num_moves];
+        let initial_commitment =
+            Self::add_board_commitment(&mut builder, initial_board, &initial_salt, &identity, start_move);
+        let final_commitment = Self::add_board_commitment(&mut builder, final_board, &final_salt, &identity, end_move);
+
+        builder.register_public_inputs(&initial_commitment.elements);
+        builder.register_public_inputs(&final_commitment.elements);
+        builder.register_public_inputs(&identity.targets());
+        builder.register_public_input(start_move);
+        builder.register_public_input(end_move);
+        builder.register_public_input(trace.timestamps[0]);
+        builder.register_public_input(trace.timestamps[num_moves - 1]);
+        builder.register_public_inputs(&trace.opening.seed.server_commitment.elements);
+        builder.register_public_inputs(&trace.opening.seed.player_entropy);
+
+        let targets =
+            CommittedTraceTargets { trace, initial_salt, final_salt, end_move, initial_commitment, final_commitment };
+        (builder, targets)
+    }
+
+    /// In-circuit counterpart of `Game2048Engine::commit_board`
+    fn add_board_commitment(
+        builder: &mut CircuitBuilder<F, D>,
+        board: &[Target; 16],
+        salt: &[Target; 4],
+        identity: &IdentityTargets,
+        move_index: Target,
+    ) -> HashOutTarget {
+        let inputs = [board.as_slice(), salt.as_slice(), &identity.targets(), &[move_index]].concat();
+        builder.hash_n_to_hash_no_pad::<PoseidonHash>(inputs)
+    }
+}
+
+/// A proof of moves between two committed boards
+#[derive(Clone, Debug)]
+pub struct CommittedTraceProof {
+    pub proof: ProofWithPublicInputs<F, C, D>,
+}
+
+impl CommittedTraceProof {
+    pub fn initial_commitment(&self) -> HashOut<F> {
+        HashOut::from_partial(&self.proof.public_inputs[0..4])
+    }
+
+    pub fn final_commitment(&self) -> HashOut<F> {
+        HashOut::from_partial(&self.proof.public_inputs[4..8])
+    }
+
+    pub fn player_identity(&self) -> PlayerIdentity {
+        PlayerIdentity::from_elements(&self.proof.public_inputs[8..8 + IDENTITY_LEN])
+    }
+
+    /// Move index of the initial board
+    pub fn start_move(&self) -> u64 {
+        self.proof.public_inputs[8 + IDENTITY_LEN].to_canonical_u64()
+    }
+
+    /// Move index of the final board
+    pub fn end_move(&self) -> u64 {
+        self.proof.public_inputs[9 + IDENTITY_LEN].to_canonical_u64()
+    }
+
+    pub fn first_timestamp(&self) -> u64 {
+        self.proof.public_inputs[10 + IDENTITY_LEN].to_canonical_u64()
+    }
+
+    pub fn last_timestamp(&self) -> u64 {
+        self.proof.public_inputs[11 + IDENTITY_LEN].to_canonical_u64()
+    }
+
+    pub fn server_commitment(&self) -> HashOut<F> {
+        HashOut::from_partial(&self.proof.public_inputs[12 + IDENTITY_LEN..16 + IDENTITY_LEN])
+    }
+
+    pub fn player_entropy(&self) -> PlayerEntropy {
+        self.proof.public_inputs[16 + IDENTITY_LEN..20 + IDENTITY_LEN].try_into().unwrap()
+    }
+
+    /// Check that the moves were played with the seed the server published a commitment to
+    pub fn check_server_commitment(&self, published: &HashOut<F>) -> Result<(), Game2048Error> {
+        if self.server_commitment() != *published {
+            return Err(Game2048Error::SeedCommitmentMismatch);
+        }
+        Ok(())
+    }
+
+    /// Check that the proof was made for this player and session
+    pub fn check_player_identity(&self, expected: &PlayerIdentity) -> Result<(), Game2048Error> {
+        if self.player_identity() != *expected {
+            return Err(Game2048Error::IdentityMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Proves moves between committed boards, with zero knowledge by default
+pub struct CommittedTraceProver {
+    circuit: ProverCircuitData<F, C, D>,
+    verifier: VerifierCircuitData<F, C, D>,
+    targets: CommittedTraceTargets,
+    rules: Box<dyn RuleSet>,
+}
+
+impl CommittedTraceProver {
+    /// Prover for a single hidden move, chained without a minimum interval
+    pub fn new() -> Self {
+        let circuit = Game2048Circuit::build_committed_circuit(CircuitProfile::ZeroKnowledge);
+        Self::from_circuit(circuit, Box::new(ClassicRules))
+    }
+
+    pub fn with_profile(num_moves: usize, min_interval: u64, profile: CircuitProfile) -> Result<Self, Game2048Error> {
+        Self::with_rules(num_moves, min_interval, profile, ClassicRules)
+    }
+
+    /// Prover for segments of a game of a 2048 variant
+    pub fn with_rules(
+        num_moves: usize,
+        min_interval: u64,
+        profile: CircuitProfile,
+        rules: impl RuleSet + 'static,
+    ) -> Result<Self, Game2048Error> {
+        let circuit =
+            Game2048Circuit::build_committed_trace_circuit_with_rules(num_moves, min_interval, profile, &rules)?;
+        Ok(Self::from_circuit(circuit, Box::new(rules)))
+    }
+
+    fn from_circuit(
+        (builder, targets): (CircuitBuilder<F, D>, CommittedTraceTargets),
+        rules: Box<dyn RuleSet>,
+    ) -> Self {
+        let circuit = builder.build::<C>();
+        let verifier = circuit.verifier_data();
+        Self { circuit: circuit.prover_data(), verifier, targets, rules }
+    }
+
+    /// Prove the moves this player plays with this seed from initial_board, the board
+    /// at move start_move, committing to the first and last boards with the given salts
+    ///
+    /// initial_board must be the seed's opening board when start_move is 0; later
+    /// segments start from the board the previous segment committed to.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prove(
+        &self,
+        server_seed: &ServerSeed,
+        player_entropy: &PlayerEntropy,
+        identity: &PlayerIdentity,
+        start_move: u64,
+        initial_board: &Board,
+        moves: &[TimedMove],
+        initial_salt: &Salt,
+        final_salt: &Salt,
+    ) -> Result<CommittedTraceProof, Game2048Error> {
+        let mut pw = PartialWitness::<F>::new();
+        self.targets.set_witness(
+            &mut pw,
+            self.rules.as_ref(),
+            server_seed,
+            player_entropy,
+            identity,
+            start_move,
+            initial_board,
+            moves,
+            initial_salt,
+            final_salt,
+        )?;
+        let proof = self.circuit.prove(pw).map_err(Game2048Error::ProofFailure)?;
+        Ok(CommittedTraceProof { proof })
+    }
+
+    /// Verifier data of the committed trace circuit, e.g. to build a `Game2048Compressor`
+    pub fn verifier_data(&self) -> &VerifierCircuitData<F, C, D> {
+        &self.verifier
+    }
+
+    pub fn verify(&self, proof: &CommittedTraceProof) -> Result<(), Game2048Error> {
+        self.verifier
+            .verify(proof.proof.clone())
+            .map_err(Game2048Error::ProofFailure)
+    }
+
+    /// Verify consecutive segments of one game, rejecting undos and rewinds
+    ///
+    /// The first segment must start at move 0, where the circuit pins its board to
+    /// the seeded opening, so a chain cannot begin from a board picked mid-game. Each
+    /// later segment must start from the commitment the previous one ended on, at
+    /// the move index it ended on, for the same player and session and with the same
+    /// seed, and its first move must follow the previous segment's last move as
+    /// `check_move_times` requires.
+    pub fn verify_chain(&self, segments: &[CommittedTraceProof]) -> Result<(), Game2048Error> {
+        for segment in segments {
+            self.verify(segment)?;
+        }
+        if segments.first().is_some_and(|first| first.start_move() != 0) {
+            return Err(Game2048Error::BrokenChain { segment: 0 });
+        }
+        for (index, pair) in segments.windows(2).enumerate() {
+            let (previous, next) = (&pair[0], &pair[1]);
+            if next.initial_commitment() != previous.final_commitment()
+                || next.start_move() != previous.end_move()
+                || next.player_identity() != previous.player_identity()
+                || next.server_commitment() != previous.server_commitment()
+                || next.player_entropy() != previous.player_entropy()
+            {
+                return Err(Game2048Error::BrokenChain { segment: index + 1 });
+            }
+            Game2048Engine::check_move_interval(
+                next.start_move() as usize,
+                previous.last_timestamp(),
+                next.first_timestamp(),
+                self.targets.trace.min_interval,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for CommittedTraceProver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,71 @@
+//! Proof compression: a proof is verified inside a circuit built with the small-proof
+//! profile, and the outer proof is kept in its place.
+
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitData, VerifierCircuitData};
+use plonky2::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+
+use super::{CircuitProfile, Game2048Error, C, D, F};
+
+/// A compressed proof, with the public inputs of the proof it wraps
+#[derive(Clone, Debug)]
+pub struct CompressedProof {
+    pub proof: ProofWithPublicInputs<F, C, D>,
+}
+
+impl CompressedProof {
+    /// Public inputs of the original proof
+    pub fn public_inputs(&self) -> &[F] {
+        &self.proof.public_inputs
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.proof.to_bytes()
+    }
+}
+
+/// Wraps proofs of one circuit (move, trace or committed trace) into compact proofs
+pub struct Game2048Compressor {
+    circuit: CircuitData<F, C, D>,
+    proof_target: ProofWithPublicInputsTarget<D>,
+}
+
+impl Game2048Compressor {
+    /// Build the wrapper circuit for proofs of the circuit with this verifier data
+    pub fn new(inner: &VerifierCircuitData<F, C, D>) -> Self {
+        Self::with_profile(inner, CircuitProfile::SmallProof)
+    }
+
+    pub fn with_profile(inner: &VerifierCircuitData<F, C, D>, profile: CircuitProfile) -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(profile.config());
+        let proof_target = builder.add_virtual_proof_with_pis(&inner.common);
+        let verifier_target = builder.constant_verifier_data(&inner.verifier_only);
+        builder.verify_proof::<C>(&proof_target, &verifier_target, &inner.common);
+
+        // Forward the public inputs, so the compressed proof states the same thing
+        builder.register_public_inputs(&proof_target.public_inputs);
+
+        let circuit = builder.build::<C>();
+        Self { circuit, proof_target }
+    }
+
+    pub fn compress(&self, proof: &ProofWithPublicInputs<F, C, D>) -> Result<CompressedProof, Game2048Error> {
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_proof_with_pis_target(&self.proof_target, proof)
+            .map_err(Game2048Error::WitnessConflict)?;
+        let proof = self.circuit.prove(pw).map_err(Game2048Error::ProofFailure)?;
+        Ok(CompressedProof { proof })
+    }
+
+    pub fn verify(&self, proof: &CompressedProof) -> Result<(), Game2048Error> {
+        self.circuit
+            .verify(proof.proof.clone())
+            .map_err(Game2048Error::ProofFailure)
+    }
+
+    /// Verifier data of the wrapper circuit, e.g. for storing alongside compressed proofs
+    pub fn verifier_data(&self) -> VerifierCircuitData<F, C, D> {
+        self.circuit.verifier_data()
+    }
+}
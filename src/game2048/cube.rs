@@ -0,0 +1,258 @@
+//! Three-dimensional 2048 on a 4x4x4 cube.
+//!
+//! Cell (x, y, z) is at index z * 16 + y * 4 + x, so layer z = 0 is laid out like a
+//! 4x4 board. A move slides every tile along one axis; the 16 lines along that axis
+//! merge exactly like the rows of a board.
+
+use std::fmt;
+
+use plonky2::field::types::{Field, PrimeField64};
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{ProverCircuitData, VerifierCircuitData};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+
+use super::{CircuitProfile, ClassicRules, Game2048Circuit, Game2048Engine, Game2048Error, RuleSet, C, D, F};
+
+/// A 4x4x4 cube, indexed by z * 16 + y * 4 + x
+pub type Cube = [u64; 64];
+
+/// Move directions on a cube: the four board directions within each layer, plus
+/// toward the front (z = 0) and the back (z = 3) layer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CubeDirection {
+    Up = 0,
+    Down = 1,
+    Left = 2,
+    Right = 3,
+    Front = 4,
+    Back = 5,
+}
+
+impl CubeDirection {
+    pub const ALL: [CubeDirection; 6] = [
+        CubeDirection::Up,
+        CubeDirection::Down,
+        CubeDirection::Left,
+        CubeDirection::Right,
+        CubeDirection::Front,
+        CubeDirection::Back,
+    ];
+
+    /// Value assigned to the direction target in the circuit
+    pub fn as_u64(self) -> u64 {
+        self as u64
+    }
+
+    /// Direction for a circuit value, if it is one of 0..6
+    pub fn from_u64(value: u64) -> Option<Self> {
+        Self::ALL.into_iter().find(|direction| direction.as_u64() == value)
+    }
+}
+
+/// A cell of an after_cube that disagrees with the expected result of a move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CubeCellMismatch {
+    pub x: usize,
+    pub y: usize,
+    pub z: usize,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl fmt::Display for CubeCellMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cell ({}, {}, {}): expected {}, found {}",
+            self.x, self.y, self.z, self.expected, self.actual
+        )
+    }
+}
+
+impl Game2048Engine {
+    /// Cube cell indices of the 16 lines along the direction's axis, ordered so that
+    /// tiles slide toward index 0
+    pub fn cube_lines(direction: CubeDirection) -> [[usize; 4]; 16] {
+        let mut lines = [[0; 4]; 16];
+        for (i, line) in lines.iter_mut().enumerate() {
+            let (outer, inner) = (i / 4, i % 4);
+            for (j, cell) in line.iter_mut().enumerate() {
+                let (x, y, z) = match direction {
+                    CubeDirection::Up => (inner, j, outer),
+                    CubeDirection::Down => (inner, 3 - j, outer),
+                    CubeDirection::Left => (j, inner, outer),
+                    CubeDirection::Right => (3 - j, inner, outer),
+                    CubeDirection::Front => (inner, outer, j),
+                    CubeDirection::Back => (inner, outer, 3 - j),
+                };
+                *cell = z * 16 + y * 4 + x;
+            }
+        }
+        lines
+    }
+
+    /// Check every tile of a cube
+    pub fn validate_cube(cube: &Cube) -> Result<(), Game2048Error> {
+        ClassicRules.validate_cube(cube)
+    }
+
+    /// Apply a move to a cube
+    pub fn apply_cube_move(cube: &Cube, direction: CubeDirection) -> Cube {
+        ClassicRules.apply_cube_move(cube, direction)
+    }
+
+    /// Check a cube move before proving it, reporting invalid tiles or every cell that disagrees
+    pub fn check_cube_move(
+        before_cube: &Cube,
+        after_cube: &Cube,
+        direction: CubeDirection,
+    ) -> Result<(), Game2048Error> {
+        ClassicRules.check_cube_move(before_cube, after_cube, direction)
+    }
+}
+
+/// Targets for the public inputs of a cube move
+#[derive(Clone, Copy, Debug)]
+pub struct CubeTargets {
+    pub before_cube: [Target; 64],
+    pub after_cube: [Target; 64],
+    pub direction: Target,
+}
+
+impl CubeTargets {
+    /// Assign a cube move to the targets
+    pub fn set_witness(
+        &self,
+        pw: &mut PartialWitness<F>,
+        before_cube: &Cube,
+        after_cube: &Cube,
+        direction: CubeDirection,
+    ) -> Result<(), Game2048Error> {
+        for (&target, &tile) in self.before_cube.iter().zip(before_cube) {
+            pw.set_target(target, F::from_canonical_u64(tile))
+                .map_err(Game2048Error::WitnessConflict)?;
+        }
+        for (&target, &tile) in self.after_cube.iter().zip(after_cube) {
+            pw.set_target(target, F::from_canonical_u64(tile))
+                .map_err(Game2048Error::WitnessConflict)?;
+        }
+        pw.set_target(self.direction, F::from_canonical_u64(direction.as_u64()))
+            .map_err(Game2048Error::WitnessConflict)
+    }
+}
+
+impl Game2048Circuit {
+    /// Build the circuit for a cube move
+    ///
+    /// Public inputs are before_cube (64), after_cube (64) and direction (1). The
+    /// direction must be one of 0..6, every tile a classic tile, and both cubes are
+    /// rearranged into the 16 lines along its axis, which are merged as rows.
+    pub fn build_cube_circuit(profile: CircuitProfile) -> (CircuitBuilder<F, D>, CubeTargets) {
+        Self::build_cube_circuit_with_rules(profile, &ClassicRules)
+    }
+
+    /// Build the cube circuit for a 2048 variant, whose tiles and merges replace the classic ones
+    pub fn build_cube_circuit_with_rules(
+        profile: CircuitProfile,
+        rules: &dyn RuleSet,
+    ) -> (CircuitBuilder<F, D>, CubeTargets) {
+        let mut builder = CircuitBuilder::<F, D>::new(profile.config());
+        let before_cube = builder.add_virtual_target_arr::<64>();
+        let after_cube = builder.add_virtual_target_arr::<64>();
+        let direction = builder.add_virtual_target();
+        builder.register_public_inputs(&before_cube);
+        builder.register_public_inputs(&after_cube);
+        builder.register_public_input(direction);
+
+        for &tile in before_cube.iter().chain(&after_cube) {
+            rules.add_tile_check(&mut builder, tile);
+        }
+        let direction_index = Self::range_checked_index(&mut builder, direction, CubeDirection::ALL.len());
+        let lines_by_direction = CubeDirection::ALL.map(Game2048Engine::cube_lines);
+        let before_lines = Self::orient_lines(&mut builder, &before_cube, &lines_by_direction, direction_index);
+        let after_lines = Self::orient_lines(&mut builder, &after_cube, &lines_by_direction, direction_index);
+        for (before_line, after_line) in before_lines.iter().zip(&after_lines) {
+            Self::validate_tiles(&mut builder, rules, before_line, after_line);
+        }
+
+        (builder, CubeTargets { before_cube, after_cube, direction })
+    }
+}
+
+/// A proof of a cube move, with both cubes and the direction as public inputs
+#[derive(Clone, Debug)]
+pub struct CubeMoveProof {
+    pub proof: ProofWithPublicInputs<F, C, D>,
+}
+
+impl CubeMoveProof {
+    fn public_cube(&self, offset: usize) -> Cube {
+        let mut cube = [0; 64];
+        for (tile, input) in cube.iter_mut().zip(&self.proof.public_inputs[offset..offset + 64]) {
+            *tile = input.to_canonical_u64();
+        }
+        cube
+    }
+
+    pub fn before_cube(&self) -> Cube {
+        self.public_cube(0)
+    }
+
+    pub fn after_cube(&self) -> Cube {
+        self.public_cube(64)
+    }
+
+    pub fn direction(&self) -> Option<CubeDirection> {
+        CubeDirection::from_u64(self.proof.public_inputs[128].to_canonical_u64())
+    }
+}
+
+/// Proves cube moves, with a circuit that is built once
+pub struct CubeProver {
+    circuit: ProverCircuitData<F, C, D>,
+    verifier: VerifierCircuitData<F, C, D>,
+    targets: CubeTargets,
+    rules: Box<dyn RuleSet>,
+}
+
+impl CubeProver {
+    pub fn new(profile: CircuitProfile) -> Self {
+        Self::with_rules(profile, ClassicRules)
+    }
+
+    /// Prover for cube moves of a 2048 variant
+    pub fn with_rules(profile: CircuitProfile, rules: impl RuleSet + 'static) -> Self {
+        let (builder, targets) = Game2048Circuit::build_cube_circuit_with_rules(profile, &rules);
+        let circuit = builder.build::<C>();
+        let verifier = circuit.verifier_data();
+        Self { circuit: circuit.prover_data(), verifier, targets, rules: Box::new(rules) }
+    }
+
+    /// Prove a cube move, after checking it with the rules' `check_cube_move`
+    pub fn prove(
+        &self,
+        before_cube: &Cube,
+        after_cube: &Cube,
+        direction: CubeDirection,
+    ) -> Result<CubeMoveProof, Game2048Error> {
+        self.rules.check_cube_move(before_cube, after_cube, direction)?;
+
+        let mut pw = PartialWitness::<F>::new();
+        self.targets.set_witness(&mut pw, before_cube, after_cube, direction)?;
+        let proof = self.circuit.prove(pw).map_err(Game2048Error::ProofFailure)?;
+        Ok(CubeMoveProof { proof })
+    }
+
+    /// Verifier data of the cube circuit, e.g. to build a `Game2048Compressor`
+    pub fn verifier_data(&self) -> &VerifierCircuitData<F, C, D> {
+        &self.verifier
+    }
+
+    pub fn verify(&self, proof: &CubeMoveProof) -> Result<(), Game2048Error> {
+        self.verifier
+            .verify(proof.proof.clone())
+            .map_err(Game2048Error::ProofFailure)
+    }
+}
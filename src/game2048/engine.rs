@@ -0,0 +1,145 @@
+use std::fmt;
+
+use super::{ClassicRules, Game2048Error, RuleSet};
+
+/// A row-major 4x4 board of tile values, 0 for an empty cell
+pub type Board = [u64; 16];
+
+/// Move directions, encoded the same way as the circuit's direction target
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up = 0,
+    Down = 1,
+    Left = 2,
+    Right = 3,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+    /// Value assigned to the direction target in the circuit
+    pub fn as_u64(self) -> u64 {
+        self as u64
+    }
+
+    /// Direction for a circuit value, if it is one of 0..4
+    pub fn from_u64(value: u64) -> Option<Self> {
+        Self::ALL.into_iter().find(|direction| direction.as_u64() == value)
+    }
+}
+
+/// A row or column of the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Line {
+    Row(usize),
+    Column(usize),
+}
+
+/// A cell of an after_board that disagrees with the expected result of a move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CellMismatch {
+    /// The line the cell was merged along
+    pub line: Line,
+    pub row: usize,
+    pub col: usize,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl fmt::Display for CellMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Line::Row(row) => write!(f, "row {}", row)?,
+            Line::Column(col) => write!(f, "column {}", col)?,
+        }
+        write!(
+            f,
+            ", cell ({}, {}): expected {}, found {}",
+            self.row, self.col, self.expected, self.actual
+        )
+    }
+}
+
+/// Native (out of circuit) reference implementation of the 2048 rules
+///
+/// These are the classic rules; other variants run the same methods on their `RuleSet`.
+pub struct Game2048Engine;
+
+impl Game2048Engine {
+    /// Whether a value can appear on the board: empty, or a power of two from 2 up
+    pub fn is_valid_tile(value: u64) -> bool {
+        ClassicRules.is_valid_tile(value)
+    }
+
+    /// Check every tile of a board
+    pub fn validate_board(board: &Board) -> Result<(), Game2048Error> {
+        ClassicRules.validate_board(board)
+    }
+
+    /// Read a board from a row-major list of tiles
+    pub fn parse_board(tiles: &[u64]) -> Result<Board, Game2048Error> {
+        let board: Board = tiles
+            .try_into()
+            .map_err(|_| Game2048Error::InvalidBoardShape { len: tiles.len() })?;
+        Self::validate_board(&board)?;
+        Ok(board)
+    }
+
+    /// Board cell indices of each line, ordered so that tiles slide toward index 0
+    pub fn lines(direction: Direction) -> [[usize; 4]; 4] {
+        let mut lines = [[0; 4]; 4];
+        for (i, line) in lines.iter_mut().enumerate() {
+            for (j, cell) in line.iter_mut().enumerate() {
+                *cell = match direction {
+                    Direction::Up => j * 4 + i,
+                    Direction::Down => (3 - j) * 4 + i,
+                    Direction::Left => i * 4 + j,
+                    Direction::Right => i * 4 + (3 - j),
+                };
+            }
+        }
+        lines
+    }
+
+    /// Merge a single row [a,b,c,d] toward the left
+    pub fn merge_row(row: [u64; 4]) -> [u64; 4] {
+        ClassicRules.merge_row(row)
+    }
+
+    /// Cells of after_board that differ from applying the move to before_board
+    pub fn diagnose_move(before_board: &Board, after_board: &Board, direction: Direction) -> Vec<CellMismatch> {
+        ClassicRules.diagnose_move(before_board, after_board, direction)
+    }
+
+    /// Cells of actual_board that differ from expected_board, by line of the direction
+    pub(crate) fn mismatches(expected_board: &Board, actual_board: &Board, direction: Direction) -> Vec<CellMismatch> {
+        let mut mismatches = Vec::new();
+        for (index, line) in Self::lines(direction).iter().enumerate() {
+            for &cell in line {
+                if expected_board[cell] != actual_board[cell] {
+                    mismatches.push(CellMismatch {
+                        line: match direction {
+                            Direction::Up | Direction::Down => Line::Column(index),
+                            Direction::Left | Direction::Right => Line::Row(index),
+                        },
+                        row: cell / 4,
+                        col: cell % 4,
+                        expected: expected_board[cell],
+                        actual: actual_board[cell],
+                    });
+                }
+            }
+        }
+        mismatches
+    }
+
+    /// Check a move before proving it, reporting invalid tiles or every cell that disagrees
+    pub fn check_move(before_board: &Board, after_board: &Board, direction: Direction) -> Result<(), Game2048Error> {
+        ClassicRules.check_move(before_board, after_board, direction)
+    }
+
+    /// Apply a move to a row-major 4x4 board
+    pub fn apply_move(board: &Board, direction: Direction) -> Board {
+        ClassicRules.apply_move(board, direction)
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+
+use super::{CellMismatch, CubeCellMismatch, CubeDirection, Direction, F};
+
+/// Errors returned by the public API
+#[derive(Debug)]
+pub enum Game2048Error {
+    /// A board did not have 16 cells
+    InvalidBoardShape { len: usize },
+    /// A cell held a tile the rules do not allow, for the classic rules anything but 0
+    /// or a power of two from 2 to 2^MAX_TILE_EXPONENT
+    InvalidTile { cell: usize, value: u64 },
+    /// The after_board does not follow from the before_board in this direction
+    IllegalMove { direction: Direction, mismatches: Vec<CellMismatch> },
+    /// The after_cube does not follow from the before_cube in this direction
+    IllegalCubeMove { direction: CubeDirection, mismatches: Vec<CubeCellMismatch> },
+    /// A wall cell held a tile
+    TileOnObstacle { cell: usize },
+    /// A trace was given a different number of moves than its circuit was built for
+    TraceLength { expected: usize, len: usize },
+    /// A trace or game circuit was requested for zero moves
+    EmptyTrace,
+    /// A revealed server seed does not match the commitment published for it
+    SeedCommitmentMismatch,
+    /// A trace starting at move 0 was given another board than the seed's opening board
+    NotOpeningBoard,
+    /// A proof was made for another player or session than expected
+    IdentityMismatch,
+    /// A move was timestamped less than the minimum interval after the previous one
+    MoveTooSoon { move_index: usize, timestamp: u64, previous: u64, min_interval: u64 },
+    /// A move was timestamped no later than the previous one
+    TimestampNotIncreasing { move_index: usize, timestamp: u64, previous: u64 },
+    /// A move was timestamped 2^32 or more ticks past the minimum interval after the previous one
+    MoveGapTooLong { move_index: usize, timestamp: u64, previous: u64 },
+    /// The first move was timestamped at 2^32 or later, or another move at the field order or later
+    TimestampOutOfRange { move_index: usize, timestamp: u64 },
+    /// A segment of a game does not continue from the previous segment
+    BrokenChain { segment: usize },
+    /// A board history inclusion proof does not match the history root
+    HistoryMismatch { move_index: usize },
+    /// The witness could not be assigned or generated
+    WitnessConflict(anyhow::Error),
+    /// Proving failed, or the proof was rejected by the verifier
+    ProofFailure(anyhow::Error),
+    /// Circuit data could not be written to bytes
+    SerializationFailure(anyhow::Error),
+    /// Proof or circuit bytes could not be read back
+    DeserializationFailure(anyhow::Error),
+    /// Verifier data belongs to a different circuit than expected
+    CircuitDigestMismatch { expected: Vec<F>, actual: Vec<F> },
+}
+
+impl fmt::Display for Game2048Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidBoardShape { len } => write!(f, "invalid board shape: {} cells instead of 16", len),
+            Self::InvalidTile { cell, value } => write!(f, "invalid tile {} in cell {}", value, cell),
+            Self::IllegalMove { direction, mismatches } => {
+                write!(f, "illegal move {:?}", direction)?;
+                for mismatch in mismatches {
+                    write!(f, "; {}", mismatch)?;
+                }
+                Ok(())
+            }
+            Self::IllegalCubeMove { direction, mismatches } => {
+                write!(f, "illegal cube move {:?}", direction)?;
+                for mismatch in mismatches {
+                    write!(f, "; {}", mismatch)?;
+                }
+                Ok(())
+            }
+            Self::TileOnObstacle { cell } => write!(f, "tile on the wall in cell {}", cell),
+            Self::TraceLength { expected, len } => {
+                write!(f, "trace of {} moves given to a circuit for {} moves", len, expected)
+            }
+            Self::EmptyTrace => write!(f, "a trace needs at least one move"),
+            Self::SeedCommitmentMismatch => write!(f, "server seed does not match its commitment"),
+            Self::NotOpeningBoard => write!(f, "initial board at move 0 is not the opening board of the seed"),
+            Self::IdentityMismatch => write!(f, "proof belongs to another player or session"),
+            Self::MoveTooSoon { move_index, timestamp, previous, min_interval } => write!(
+                f,
+                "move {} at {} is less than {} after the previous move at {}",
+                move_index, timestamp, min_interval, previous
+            ),
+            Self::TimestampNotIncreasing { move_index, timestamp, previous } => write!(
+                f,
+                "move {} at {} is not later than the previous move at {}",
+                move_index, timestamp, previous
+            ),
+            Self::MoveGapTooLong { move_index, timestamp, previous } => write!(
+                f,
+                "move {} at {} is too long after the previous move at {}",
+                move_index, timestamp, previous
+            ),
+            Self::TimestampOutOfRange { move_index, timestamp } => {
+                write!(f, "move {} is timestamped out of range at {}", move_index, timestamp)
+            }
+            Self::BrokenChain { segment } => {
+                write!(f, "segment {} does not continue from the previous segment", segment)
+            }
+            Self::HistoryMismatch { move_index } => {
+                write!(f, "board at move {} is not in the board history", move_index)
+            }
+            Self::WitnessConflict(err) => write!(f, "witness conflict: {}", err),
+            Self::ProofFailure(err) => write!(f, "proof failure: {}", err),
+            Self::SerializationFailure(err) => write!(f, "failed to serialize {}", err),
+            Self::DeserializationFailure(err) => write!(f, "failed to deserialize {}", err),
+            Self::CircuitDigestMismatch { expected, actual } => {
+                write!(f, "circuit digest mismatch: expected {:?}, got {:?}", expected, actual)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Game2048Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::WitnessConflict(err)
+            | Self::ProofFailure(err)
+            | Self::SerializationFailure(err)
+            | Self::DeserializationFailure(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! The Fibonacci variant: tiles are Fibonacci numbers, and neighbouring consecutive
+//! Fibonacci numbers (1+1, 1+2, 2+3, 3+5, ...) merge into their sum.
+//!
+//! Consecutive Fibonacci numbers are exactly the positive solutions of
+//! (y^2 - xy - x^2)^2 = 1 with x <= y, which is what the circuit checks, in both
+//! orders. This only holds over the integers, so the circuit bounds every tile below
+//! 2^30 so that the form cannot wrap around the field. The same identity with y the
+//! next Fibonacci number, given as a hint, shows a tile is a Fibonacci number at all.
+
+use anyhow::Result;
+use plonky2::field::extension::Extendable;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+
+use super::{Game2048Circuit, RuleSet, D, F};
+
+/// Tiles are below 2^TILE_BITS
+const TILE_BITS: usize = 30;
+
+/// The largest tile, the largest Fibonacci number below 2^30
+pub const MAX_FIBONACCI_TILE: u64 = 701_408_733;
+
+/// One spawn in this many is a 2
+const TWO_ODDS: u64 = 10;
+
+/// The Fibonacci number after value, or None if value is not a Fibonacci number
+///
+/// 0 is followed by 1 and 1 by 1, so every tile, including an empty cell, has one.
+fn next_fibonacci(value: u64) -> Option<u64> {
+    let (mut current, mut next) = (0, 1);
+    while current < value {
+        (current, next) = (next, current + next);
+    }
+    (current == value).then_some(next)
+}
+
+/// y^2 - xy - x^2, which is ±1 exactly when x and y are consecutive Fibonacci numbers
+fn fibonacci_form(x: u64, y: u64) -> i128 {
+    let (x, y) = (x as i128, y as i128);
+    y * y - x * y - x * x
+}
+
+/// Fibonacci 2048: 1 or 2 spawns, and consecutive Fibonacci numbers merge into their
+/// sum, which is also the score
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FibonacciRules;
+
+impl FibonacciRules {
+    /// In-circuit (y^2 - xy - x^2)^2
+    fn add_squared_form(builder: &mut CircuitBuilder<F, D>, x: Target, y: Target) -> Target {
+        let xy = builder.mul(x, y);
+        let x_squared = builder.mul(x, x);
+        let y_squared = builder.mul(y, y);
+        let form = builder.sub(y_squared, xy);
+        let form = builder.sub(form, x_squared);
+        builder.mul(form, form)
+    }
+}
+
+impl RuleSet for FibonacciRules {
+    fn name(&self) -> &'static str {
+        "fibonacci"
+    }
+
+    fn is_valid_tile(&self, value: u64) -> bool {
+        value <= MAX_FIBONACCI_TILE && next_fibonacci(value).is_some()
+    }
+
+    fn can_merge(&self, a: u64, b: u64) -> bool {
+        a != 0 && b != 0 && (fibonacci_form(a, b).abs() == 1 || fibonacci_form(b, a).abs() == 1)
+    }
+
+    fn merged_value(&self, a: u64, b: u64) -> u64 {
+        a + b
+    }
+
+    fn spawn_value(&self, random: u64) -> u64 {
+        if random.is_multiple_of(TWO_ODDS) { 2 } else { 1 }
+    }
+
+    /// Sound for tiles below 2^30, which `add_tile_check` enforces on both boards
+    fn add_can_merge(&self, builder: &mut CircuitBuilder<F, D>, a: Target, b: Target) -> BoolTarget {
+        let zero = builder.zero();
+        let one = builder.one();
+        let a_is_zero = builder.is_equal(a, zero);
+        let b_is_zero = builder.is_equal(b, zero);
+        let either_zero = builder.or(a_is_zero, b_is_zero);
+        let both_nonzero = builder.not(either_zero);
+
+        let ascending = Self::add_squared_form(builder, a, b);
+        let descending = Self::add_squared_form(builder, b, a);
+        let ascending = builder.is_equal(ascending, one);
+        let descending = builder.is_equal(descending, one);
+        let consecutive = builder.or(ascending, descending);
+        builder.and(consecutive, both_nonzero)
+    }
+
+    fn add_merged_value(&self, builder: &mut CircuitBuilder<F, D>, a: Target, b: Target) -> Target {
+        builder.add(a, b)
+    }
+
+    /// The tile is below 2^30 and, with the next Fibonacci number as a hint, a
+    /// Fibonacci number
+    fn add_tile_check(&self, builder: &mut CircuitBuilder<F, D>, tile: Target) {
+        let next = builder.add_virtual_target();
+        builder.add_simple_generator(NextFibonacciGenerator { tile, next });
+
+        Game2048Circuit::range_check_bits(builder, tile, TILE_BITS);
+        Game2048Circuit::range_check_bits(builder, next, TILE_BITS + 1);
+        let squared_form = Self::add_squared_form(builder, tile, next);
+        let one = builder.one();
+        builder.connect(squared_form, one);
+    }
+
+    fn add_spawn_value(&self, builder: &mut CircuitBuilder<F, D>, random: Target) -> Target {
+        let zero = builder.zero();
+        let one = builder.one();
+        let ten = builder.constant(F::from_canonical_u64(TWO_ODDS));
+        let (_, odds) = Game2048Circuit::div_rem(builder, random, ten);
+        let is_two = builder.is_equal(odds, zero);
+        builder.add(one, is_two.target)
+    }
+}
+
+/// Writes the Fibonacci number after a tile, or 0 for a tile that is not one, which
+/// then fails `add_tile_check`
+#[derive(Debug, Default)]
+struct NextFibonacciGenerator {
+    tile: Target,
+    next: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for NextFibonacciGenerator {
+    fn id(&self) -> String {
+        "NextFibonacciGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.tile]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> Result<()> {
+        let tile = witness.get_target(self.tile).to_canonical_u64();
+        let next = if tile <= MAX_FIBONACCI_TILE { next_fibonacci(tile).unwrap_or(0) } else { 0 };
+        out_buffer.set_target(self.next, F::from_canonical_u64(next))
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target(self.tile)?;
+        dst.write_target(self.next)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let tile = src.read_target()?;
+        let next = src.read_target()?;
+        Ok(Self { tile, next })
+    }
+}
@@ -0,0 +1,381 @@
+//! Game circuits: boards derived from the committed seed rather than chosen by the prover.
+//!
+//! A game starts from the opening board, two spawns (indices 0 and 1) on an empty
+//! board. Move n is followed by spawn number OPENING_TILES + n.
+//!
+//! The proof exposes the root of the game's `BoardHistory` rather than every board.
+//!
+//! Every move carries a timestamp (a tick counter in any unit). Timestamps must
+//! increase by at least the circuit's minimum interval, and at least 1, from one
+//! move to the next, so that games played faster than a human could are rejected.
+//! The first timestamp of a proof is below 2^32, e.g. ticks since the session started,
+//! so that no timestamp of a proof wraps around the field.
+
+use plonky2::field::types::{Field, Field64, PrimeField64};
+use plonky2::hash::hash_types::{HashOut, HashOutTarget};
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{ProverCircuitData, VerifierCircuitData};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+
+use super::{
+    Board, CircuitProfile, ClassicRules, Direction, Game2048Circuit, Game2048Engine, Game2048Error, LeaderboardStats,
+    PlayerEntropy, PlayerIdentity, RuleSet, SeedTargets, ServerSeed, TraceTargets, C, D, F, IDENTITY_LEN,
+    LEADERBOARD_LEN,
+};
+
+/// Number of tiles spawned on the empty board before the first move
+pub const OPENING_TILES: u64 = 2;
+
+/// Consecutive timestamps are less than 2^INTERVAL_BITS ticks further apart than the minimum interval
+const INTERVAL_BITS: usize = 32;
+
+/// The first timestamp of a proof is below 2^FIRST_TIMESTAMP_BITS
+const FIRST_TIMESTAMP_BITS: usize = 32;
+
+/// A move as recorded during play
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimedMove {
+    pub direction: Direction,
+    pub timestamp: u64,
+}
+
+impl Game2048Engine {
+    /// The opening board of a game with this seed
+    pub fn opening_board(seed: &HashOut<F>) -> Board {
+        ClassicRules.opening_board(seed)
+    }
+
+    /// Every board of a game: the opening board, then the board after each move and its spawn
+    pub fn play(seed: &HashOut<F>, directions: &[Direction]) -> Vec<Board> {
+        ClassicRules.play(seed, directions)
+    }
+
+    /// The boards reached from initial_board, the board at move start_move, by each
+    /// move and its spawn, starting with initial_board
+    pub fn play_from(
+        seed: &HashOut<F>,
+        initial_board: &Board,
+        start_move: u64,
+        directions: &[Direction],
+    ) -> Vec<Board> {
+        ClassicRules.play_from(seed, initial_board, start_move, directions)
+    }
+
+    /// Check that the first timestamp is below 2^32 and that each timestamp follows the
+    /// previous one by at least min_interval (and at least 1), and by less than 2^32 ticks
+    /// more than that
+    pub fn check_move_times(moves: &[TimedMove], min_interval: u64) -> Result<(), Game2048Error> {
+        for (move_index, timed_move) in moves.iter().enumerate() {
+            let limit = if move_index == 0 { 1 << FIRST_TIMESTAMP_BITS } else { F::ORDER };
+            if timed_move.timestamp >= limit {
+                return Err(Game2048Error::TimestampOutOfRange { move_index, timestamp: timed_move.timestamp });
+            }
+        }
+        for (index, pair) in moves.windows(2).enumerate() {
+            Self::check_move_interval(index + 1, pair[0].timestamp, pair[1].timestamp, min_interval)?;
+        }
+        Ok(())
+    }
+
+    /// `check_move_times` for a single pair of timestamps, of move move_index and the one before it
+    pub(crate) fn check_move_interval(
+        move_index: usize,
+        previous: u64,
+        timestamp: u64,
+        min_interval: u64,
+    ) -> Result<(), Game2048Error> {
+        if timestamp <= previous {
+            return Err(Game2048Error::TimestampNotIncreasing { move_index, timestamp, previous });
+        }
+        let interval = timestamp - previous;
+        if interval < min_interval.max(1) {
+            return Err(Game2048Error::MoveTooSoon { move_index, timestamp, previous, min_interval });
+        }
+        if (interval - min_interval.max(1)) >> INTERVAL_BITS != 0 {
+            return Err(Game2048Error::MoveGapTooLong { move_index, timestamp, previous });
+        }
+        Ok(())
+    }
+}
+
+/// Targets of the opening board derivation
+#[derive(Clone, Copy, Debug)]
+pub struct OpeningTargets {
+    pub seed: SeedTargets,
+    /// The derived opening board
+    pub board: [Target; 16],
+}
+
+/// Targets of a game: the moves from the opening board and the history of the boards they lead to
+#[derive(Clone, Debug)]
+pub struct GameTargets {
+    /// The seed, the timed moves and the boards, starting at move 0
+    pub trace: TraceTargets,
+    /// Root of the `BoardHistory` over boards and directions
+    pub history_root: HashOutTarget,
+}
+
+impl GameTargets {
+    pub fn num_moves(&self) -> usize {
+        self.trace.num_moves()
+    }
+
+    /// Assign the seed and the moves played under rules, returning every board of the game
+    pub fn set_witness(
+        &self,
+        pw: &mut PartialWitness<F>,
+        rules: &dyn RuleSet,
+        server_seed: &ServerSeed,
+        player_entropy: &PlayerEntropy,
+        identity: &PlayerIdentity,
+        moves: &[TimedMove],
+    ) -> Result<Vec<Board>, Game2048Error> {
+        self.trace.set_witness(pw, rules, server_seed, player_entropy, identity, moves)
+    }
+}
+
+impl Game2048Circuit {
+    /// Build a circuit deriving the opening board from the seed
+    ///
+    /// Public inputs are the server's seed commitment (4), the player entropy (4),
+    /// the player identity (5) and the opening board (16).
+    pub fn build_opening_circuit(profile: CircuitProfile) -> (CircuitBuilder<F, D>, OpeningTargets) {
+        let mut builder = CircuitBuilder::<F, D>::new(profile.config());
+        let targets = Self::add_opening(&mut builder, &ClassicRules);
+
+        builder.register_public_inputs(&targets.seed.server_commitment.elements);
+        builder.register_public_inputs(&targets.seed.player_entropy);
+        builder.register_public_inputs(&targets.seed.identity.targets());
+        builder.register_public_inputs(&targets.board);
+
+        (builder, targets)
+    }
+
+    /// Build a circuit for a game of num_moves moves from the opening board, at
+    /// least min_interval ticks apart
+    ///
+    /// Public inputs are the server's seed commitment (4), the player entropy (4),
+    /// the player identity (5), the final board (16), the board history root (4), the
+    /// directions (num_moves), the first and last timestamps (2) and the leaderboard
+    /// fields (LEADERBOARD_LEN).
+    pub fn build_game_circuit(
+        num_moves: usize,
+        min_interval: u64,
+        profile: CircuitProfile,
+    ) -> Result<(CircuitBuilder<F, D>, GameTargets), Game2048Error> {
+        Self::build_game_circuit_with_rules(num_moves, min_interval, profile, &ClassicRules)
+    }
+
+    /// Build the game circuit for a 2048 variant, whose rules decide the moves and the spawns
+    pub fn build_game_circuit_with_rules(
+        num_moves: usize,
+        min_interval: u64,
+        profile: CircuitProfile,
+        rules: &dyn RuleSet,
+    ) -> Result<(CircuitBuilder<F, D>, GameTargets), Game2048Error> {
+        if num_moves == 0 {
+            return Err(Game2048Error::EmptyTrace);
+        }
+        let mut builder = CircuitBuilder::<F, D>::new(profile.config());
+        let targets = Self::add_game(&mut builder, rules, num_moves, min_interval);
+
+        let trace = &targets.trace;
+        builder.register_public_inputs(&trace.opening.seed.server_commitment.elements);
+        builder.register_public_inputs(&trace.opening.seed.player_entropy);
+        builder.register_public_inputs(&trace.opening.seed.identity.targets());
+        builder.register_public_inputs(&trace.boards[num_moves]);
+        builder.register_public_inputs(&targets.history_root.elements);
+        builder.register_public_inputs(&trace.directions);
+        builder.register_public_input(trace.timestamps[0]);
+        builder.register_public_input(trace.timestamps[num_moves - 1]);
+        builder.register_public_inputs(&trace.leaderboard.targets());
+
+        Ok((builder, targets))
+    }
+
+    /// Add the seed and the opening board the rules derive from it
+    pub(crate) fn add_opening(builder: &mut CircuitBuilder<F, D>, rules: &dyn RuleSet) -> OpeningTargets {
+        let seed = Self::add_seed(builder);
+        let mut board = [builder.zero(); 16];
+        for spawn_index in 0..OPENING_TILES {
+            let spawn_index = builder.constant(F::from_canonical_u64(spawn_index));
+            board = Self::add_spawn_with_rules(builder, rules, &board, seed.seed, spawn_index, None);
+        }
+        OpeningTargets { seed, board }
+    }
+
+    /// Add a game of num_moves moves, each followed by its spawn, without registering public inputs
+    pub(crate) fn add_game(
+        builder: &mut CircuitBuilder<F, D>,
+        rules: &dyn RuleSet,
+        num_moves: usize,
+        min_interval: u64,
+    ) -> GameTargets {
+        let trace = Self::add_trace(builder, rules, num_moves, min_interval);
+        builder.assert_zero(trace.start_move);
+        let history_root = Self::add_history_root(builder, &trace.boards, &trace.directions);
+        GameTargets { trace, history_root }
+    }
+
+    /// Add a timestamp for each of num_moves moves, each at least min_interval after the previous one
+    ///
+    /// The first timestamp is range checked too, so the sums of intervals up to the last
+    /// one stay far below the field order.
+    pub(crate) fn add_timestamps(
+        builder: &mut CircuitBuilder<F, D>,
+        num_moves: usize,
+        min_interval: u64,
+    ) -> Vec<Target> {
+        let timestamps = builder.add_virtual_targets(num_moves);
+        Self::range_check_bits(builder, timestamps[0], FIRST_TIMESTAMP_BITS);
+        for pair in timestamps.windows(2) {
+            Self::add_interval_check(builder, pair[0], pair[1], min_interval);
+        }
+        timestamps
+    }
+
+    /// Constrain later - earlier - max(min_interval, 1) to INTERVAL_BITS bits, so the
+    /// timestamps strictly increase by at least min_interval and at most about 2^32
+    fn add_interval_check(builder: &mut CircuitBuilder<F, D>, earlier: Target, later: Target, min_interval: u64) {
+        let interval = builder.sub(later, earlier);
+        let min_interval = builder.constant(F::from_canonical_u64(min_interval.max(1)));
+        let slack = builder.sub(interval, min_interval);
+        Self::range_check_bits(builder, slack, INTERVAL_BITS);
+    }
+}
+
+/// Index of the final board in a game proof's public inputs
+const BOARD_START: usize = 8 + IDENTITY_LEN;
+/// Index of the board history root, after the final board
+const HISTORY_START: usize = BOARD_START + 16;
+/// Index of the directions, after the history root
+const DIRECTIONS_START: usize = HISTORY_START + 4;
+
+/// A proof of a game played from the opening board of a committed seed
+#[derive(Clone, Debug)]
+pub struct Game2048GameProof {
+    pub proof: ProofWithPublicInputs<F, C, D>,
+}
+
+impl Game2048GameProof {
+    pub fn server_commitment(&self) -> HashOut<F> {
+        HashOut::from_partial(&self.proof.public_inputs[0..4])
+    }
+
+    pub fn player_entropy(&self) -> PlayerEntropy {
+        self.proof.public_inputs[4..8].try_into().unwrap()
+    }
+
+    pub fn player_identity(&self) -> PlayerIdentity {
+        PlayerIdentity::from_elements(&self.proof.public_inputs[8..8 + IDENTITY_LEN])
+    }
+
+    pub fn final_board(&self) -> Board {
+        let mut board = [0; 16];
+        for (tile, input) in board.iter_mut().zip(&self.proof.public_inputs[BOARD_START..BOARD_START + 16]) {
+            *tile = input.to_canonical_u64();
+        }
+        board
+    }
+
+    /// Root of the game's `BoardHistory`, to check inclusion proofs against
+    pub fn history_root(&self) -> HashOut<F> {
+        HashOut::from_partial(&self.proof.public_inputs[HISTORY_START..DIRECTIONS_START])
+    }
+
+    pub fn directions(&self) -> Vec<Option<Direction>> {
+        let num_moves = self.proof.public_inputs.len() - DIRECTIONS_START - 2 - LEADERBOARD_LEN;
+        self.proof.public_inputs[DIRECTIONS_START..DIRECTIONS_START + num_moves]
+            .iter()
+            .map(|input| Direction::from_u64(input.to_canonical_u64()))
+            .collect()
+    }
+
+    pub fn first_timestamp(&self) -> u64 {
+        self.proof.public_inputs[self.leaderboard_start() - 2].to_canonical_u64()
+    }
+
+    pub fn last_timestamp(&self) -> u64 {
+        self.proof.public_inputs[self.leaderboard_start() - 1].to_canonical_u64()
+    }
+
+    pub fn leaderboard_stats(&self) -> LeaderboardStats {
+        LeaderboardStats::from_elements(&self.proof.public_inputs[self.leaderboard_start()..])
+    }
+
+    fn leaderboard_start(&self) -> usize {
+        self.proof.public_inputs.len() - LEADERBOARD_LEN
+    }
+
+    /// Check that the game was played with the seed the server published a commitment to
+    pub fn check_server_commitment(&self, published: &HashOut<F>) -> Result<(), Game2048Error> {
+        if self.server_commitment() != *published {
+            return Err(Game2048Error::SeedCommitmentMismatch);
+        }
+        Ok(())
+    }
+
+    /// Check that the proof was made for this player and session
+    pub fn check_player_identity(&self, expected: &PlayerIdentity) -> Result<(), Game2048Error> {
+        if self.player_identity() != *expected {
+            return Err(Game2048Error::IdentityMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Proves games of a fixed number of moves
+pub struct Game2048GameProver {
+    circuit: ProverCircuitData<F, C, D>,
+    verifier: VerifierCircuitData<F, C, D>,
+    targets: GameTargets,
+    rules: Box<dyn RuleSet>,
+}
+
+impl Game2048GameProver {
+    pub fn new(num_moves: usize, min_interval: u64, profile: CircuitProfile) -> Result<Self, Game2048Error> {
+        Self::with_rules(num_moves, min_interval, profile, ClassicRules)
+    }
+
+    /// Prover for games of a 2048 variant
+    pub fn with_rules(
+        num_moves: usize,
+        min_interval: u64,
+        profile: CircuitProfile,
+        rules: impl RuleSet + 'static,
+    ) -> Result<Self, Game2048Error> {
+        let (builder, targets) =
+            Game2048Circuit::build_game_circuit_with_rules(num_moves, min_interval, profile, &rules)?;
+        let circuit = builder.build::<C>();
+        let verifier = circuit.verifier_data();
+        Ok(Self { circuit: circuit.prover_data(), verifier, targets, rules: Box::new(rules) })
+    }
+
+    /// Prove the game played by this player with these moves from the seed's opening board
+    pub fn prove(
+        &self,
+        server_seed: &ServerSeed,
+        player_entropy: &PlayerEntropy,
+        identity: &PlayerIdentity,
+        moves: &[TimedMove],
+    ) -> Result<Game2048GameProof, Game2048Error> {
+        let mut pw = PartialWitness::<F>::new();
+        self.targets
+            .set_witness(&mut pw, self.rules.as_ref(), server_seed, player_entropy, identity, moves)?;
+        let proof = self.circuit.prove(pw).map_err(Game2048Error::ProofFailure)?;
+        Ok(Game2048GameProof { proof })
+    }
+
+    /// Verifier data of the game circuit, e.g. to build a `Game2048Compressor`
+    pub fn verifier_data(&self) -> &VerifierCircuitData<F, C, D> {
+        &self.verifier
+    }
+
+    pub fn verify(&self, proof: &Game2048GameProof) -> Result<(), Game2048Error> {
+        self.verifier
+            .verify(proof.proof.clone())
+            .map_err(Game2048Error::ProofFailure)
+    }
+}
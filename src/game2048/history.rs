@@ -0,0 +1,126 @@
+//! Board history: a Merkle tree over every board of a game and the move played from it.
+//!
+//! Leaf i is (i, the 16 tiles of board i, the direction played from board i), with
+//! `NO_DIRECTION` for the final board. Leaves are padded with empty leaves to a power
+//! of two and hashed as in plonky2's `MerkleTree` with Poseidon. A game proof exposes
+//! only the root; the player keeps the `BoardHistory` and can later show that a board
+//! occurred at a given move, e.g. to settle a dispute.
+
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::{HashOut, HashOutTarget};
+use plonky2::hash::merkle_proofs::{verify_merkle_proof, MerkleProof};
+use plonky2::hash::merkle_tree::MerkleTree;
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::target::Target;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+use super::{Board, Direction, Game2048Circuit, Game2048Error, D, F};
+
+/// Direction value of the final board's leaf, where no move has been played yet
+pub const NO_DIRECTION: u64 = 4;
+
+fn history_leaf(move_index: usize, board: &Board, direction: Option<Direction>) -> Vec<F> {
+    let direction = direction.map_or(NO_DIRECTION, Direction::as_u64);
+    [move_index as u64]
+        .iter()
+        .chain(board)
+        .chain(&[direction])
+        .map(|&value| F::from_canonical_u64(value))
+        .collect()
+}
+
+/// Every board of a game with the moves played from them, committed to by a Merkle root
+#[derive(Clone, Debug)]
+pub struct BoardHistory {
+    boards: Vec<Board>,
+    directions: Vec<Direction>,
+    tree: MerkleTree<F, PoseidonHash>,
+}
+
+impl BoardHistory {
+    /// History of a game, with one more board than directions
+    pub fn new(boards: &[Board], directions: &[Direction]) -> Result<Self, Game2048Error> {
+        if boards.len() != directions.len() + 1 {
+            return Err(Game2048Error::TraceLength { expected: directions.len(), len: boards.len().saturating_sub(1) });
+        }
+
+        let mut leaves: Vec<_> = boards
+            .iter()
+            .enumerate()
+            .map(|(i, board)| history_leaf(i, board, directions.get(i).copied()))
+            .collect();
+        leaves.resize(leaves.len().next_power_of_two(), Vec::new());
+
+        let tree = MerkleTree::new(leaves, 0);
+        Ok(Self { boards: boards.to_vec(), directions: directions.to_vec(), tree })
+    }
+
+    pub fn root(&self) -> HashOut<F> {
+        self.tree.cap.0[0]
+    }
+
+    /// Inclusion proof of the board before move move_index (the final board for the last index)
+    pub fn prove_inclusion(&self, move_index: usize) -> Option<HistoryInclusion> {
+        let board = *self.boards.get(move_index)?;
+        Some(HistoryInclusion {
+            move_index,
+            board,
+            direction: self.directions.get(move_index).copied(),
+            proof: self.tree.prove(move_index),
+        })
+    }
+}
+
+/// Shows that a board, and the move played from it, occurred at a move of a game
+#[derive(Clone, Debug)]
+pub struct HistoryInclusion {
+    pub move_index: usize,
+    pub board: Board,
+    /// None for the final board
+    pub direction: Option<Direction>,
+    pub proof: MerkleProof<F, PoseidonHash>,
+}
+
+impl HistoryInclusion {
+    /// Check the inclusion against the history root of a game proof
+    pub fn verify(&self, root: &HashOut<F>) -> Result<(), Game2048Error> {
+        let leaf = history_leaf(self.move_index, &self.board, self.direction);
+        verify_merkle_proof(leaf, self.move_index, *root, &self.proof)
+            .map_err(|_| Game2048Error::HistoryMismatch { move_index: self.move_index })
+    }
+}
+
+impl Game2048Circuit {
+    /// In-circuit counterpart of `BoardHistory::root`, for boards with one move between each
+    pub(crate) fn add_history_root(
+        builder: &mut CircuitBuilder<F, D>,
+        boards: &[[Target; 16]],
+        directions: &[Target],
+    ) -> HashOutTarget {
+        let no_direction = builder.constant(F::from_canonical_u64(NO_DIRECTION));
+        let mut layer: Vec<_> = boards
+            .iter()
+            .enumerate()
+            .map(|(i, board)| {
+                let index = builder.constant(F::from_canonical_usize(i));
+                let direction = directions.get(i).copied().unwrap_or(no_direction);
+                let inputs = [&[index], board.as_slice(), &[direction]].concat();
+                builder.hash_n_to_hash_no_pad::<PoseidonHash>(inputs)
+            })
+            .collect();
+
+        // Empty leaves hash to zero, and a node is Poseidon(left || right)
+        let empty_leaf = builder.constant_hash(HashOut::ZERO);
+        layer.resize(layer.len().next_power_of_two(), empty_leaf);
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(2)
+                .map(|pair| {
+                    let inputs = [pair[0].elements, pair[1].elements].concat();
+                    builder.hash_n_to_hash_no_pad::<PoseidonHash>(inputs)
+                })
+                .collect();
+        }
+        layer[0]
+    }
+}
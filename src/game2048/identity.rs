@@ -0,0 +1,79 @@
+//! Player identity and session binding.
+//!
+//! A `PlayerIdentity` is the Poseidon hash of the player's public key and a
+//! nonce for the session. Circuits absorb it into the spawn seed and the board
+//! commitments and expose it as public inputs, so a proof is only meaningful for
+//! the player and session it was created for: claiming it under another identity
+//! changes the public inputs, and proving the same game for another identity
+//! gives a different seed and different commitments.
+
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::{HashOut, HashOutTarget};
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::config::Hasher;
+
+use super::{Game2048Circuit, Game2048Error, D, F};
+
+/// Number of public inputs taken by an identity: the player id (4) and the session nonce (1)
+pub const IDENTITY_LEN: usize = 5;
+
+/// The player a proof is made for, and the session it belongs to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayerIdentity {
+    /// Poseidon hash of the player's public key
+    pub player_id: HashOut<F>,
+    pub session_nonce: F,
+}
+
+impl PlayerIdentity {
+    pub fn new(public_key: &[F], session_nonce: u64) -> Self {
+        Self { player_id: PoseidonHash::hash_no_pad(public_key), session_nonce: F::from_canonical_u64(session_nonce) }
+    }
+
+    /// The identity as field elements, in public input order
+    pub fn elements(&self) -> [F; IDENTITY_LEN] {
+        let [a, b, c, d] = self.player_id.elements;
+        [a, b, c, d, self.session_nonce]
+    }
+
+    /// Read an identity back from public inputs
+    pub fn from_elements(elements: &[F]) -> Self {
+        Self { player_id: HashOut::from_partial(&elements[0..4]), session_nonce: elements[4] }
+    }
+}
+
+/// Targets of a player identity
+#[derive(Clone, Copy, Debug)]
+pub struct IdentityTargets {
+    pub player_id: HashOutTarget,
+    pub session_nonce: Target,
+}
+
+impl IdentityTargets {
+    pub fn set_witness(&self, pw: &mut PartialWitness<F>, identity: &PlayerIdentity) -> Result<(), Game2048Error> {
+        pw.set_hash_target(self.player_id, identity.player_id)
+            .map_err(Game2048Error::WitnessConflict)?;
+        pw.set_target(self.session_nonce, identity.session_nonce)
+            .map_err(Game2048Error::WitnessConflict)
+    }
+
+    /// The identity as targets, in public input order
+    pub fn targets(&self) -> [Target; IDENTITY_LEN] {
+        let [a, b, c, d] = self.player_id.elements;
+        [a, b, c, d, self.session_nonce]
+    }
+}
+
+impl Game2048Circuit {
+    /// Add an identity, without registering it as public inputs
+    ///
+    /// Circuits should register `targets()` and absorb them into their seed or commitments.
+    pub fn add_identity(builder: &mut CircuitBuilder<F, D>) -> IdentityTargets {
+        let player_id = builder.add_virtual_hash();
+        let session_nonce = builder.add_virtual_target();
+        IdentityTargets { player_id, session_nonce }
+    }
+}
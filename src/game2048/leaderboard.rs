@@ -0,0 +1,142 @@
+//! Leaderboard fields proven by the trace and game circuits.
+//!
+//! Tiles only ever merge into larger ones, so the highest tile ever reached is the
+//! highest tile of the final board.
+
+use anyhow::Result;
+use plonky2::field::extension::Extendable;
+use plonky2::field::types::{Field, PrimeField64};
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+
+use super::{Board, Direction, Game2048Circuit, Game2048Engine, D, F};
+
+/// Number of public inputs taken by the leaderboard fields
+pub const LEADERBOARD_LEN: usize = 6;
+
+/// Leaderboard fields of a game
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LeaderboardStats {
+    pub num_moves: u64,
+    pub max_tile: u64,
+    /// Moves per direction, indexed by `Direction::as_u64`
+    pub direction_counts: [u64; 4],
+}
+
+impl LeaderboardStats {
+    /// Read the fields back from public inputs
+    pub fn from_elements(elements: &[F]) -> Self {
+        let values: Vec<u64> = elements[..LEADERBOARD_LEN].iter().map(|input| input.to_canonical_u64()).collect();
+        Self { num_moves: values[0], max_tile: values[1], direction_counts: values[2..6].try_into().unwrap() }
+    }
+}
+
+impl Game2048Engine {
+    /// Leaderboard fields of a game that ended on final_board
+    pub fn leaderboard_stats(final_board: &Board, directions: &[Direction]) -> LeaderboardStats {
+        let mut direction_counts = [0; 4];
+        for direction in directions {
+            direction_counts[direction.as_u64() as usize] += 1;
+        }
+        LeaderboardStats {
+            num_moves: directions.len() as u64,
+            max_tile: final_board.iter().copied().max().unwrap_or(0),
+            direction_counts,
+        }
+    }
+}
+
+/// Targets of the leaderboard fields, in public input order
+#[derive(Clone, Copy, Debug)]
+pub struct LeaderboardTargets {
+    pub num_moves: Target,
+    pub max_tile: Target,
+    pub direction_counts: [Target; 4],
+}
+
+impl LeaderboardTargets {
+    pub fn targets(&self) -> [Target; LEADERBOARD_LEN] {
+        let [up, down, left, right] = self.direction_counts;
+        [self.num_moves, self.max_tile, up, down, left, right]
+    }
+}
+
+impl Game2048Circuit {
+    /// Derive the leaderboard fields from the final board and the direction targets
+    /// of a sequence of moves, without registering them as public inputs
+    pub(crate) fn add_leaderboard(
+        builder: &mut CircuitBuilder<F, D>,
+        final_board: &[Target; 16],
+        directions: &[Target],
+    ) -> LeaderboardTargets {
+        let num_moves = builder.constant(F::from_canonical_usize(directions.len()));
+        let direction_counts = Direction::ALL.map(|direction| {
+            let direction_const = builder.constant(F::from_canonical_u64(direction.as_u64()));
+            let flags: Vec<_> = directions
+                .iter()
+                .map(|&target| builder.is_equal(target, direction_const).target)
+                .collect();
+            builder.add_many(flags)
+        });
+        let max_tile = Self::add_max_tile(builder, final_board);
+        LeaderboardTargets { num_moves, max_tile, direction_counts }
+    }
+
+    /// The highest tile of a board: no tile exceeds it, and it is one of the tiles
+    fn add_max_tile(builder: &mut CircuitBuilder<F, D>, board: &[Target; 16]) -> Target {
+        let max_tile = builder.add_virtual_target();
+        builder.add_simple_generator(MaxTileGenerator { tiles: board.to_vec(), max_tile });
+
+        let mut product = builder.one();
+        for &tile in board {
+            let slack = builder.sub(max_tile, tile);
+            Self::range_check_bits(builder, slack, 32);
+            product = builder.mul(product, slack);
+        }
+        builder.assert_zero(product);
+        max_tile
+    }
+}
+
+/// Computes the highest tile checked by `add_max_tile`
+#[derive(Debug, Default)]
+struct MaxTileGenerator {
+    tiles: Vec<Target>,
+    max_tile: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for MaxTileGenerator {
+    fn id(&self) -> String {
+        "MaxTileGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        self.tiles.clone()
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> Result<()> {
+        let max_tile = self
+            .tiles
+            .iter()
+            .map(|&tile| witness.get_target(tile).to_canonical_u64())
+            .max()
+            .unwrap_or(0);
+        out_buffer.set_target(self.max_tile, F::from_canonical_u64(max_tile))
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.tiles)?;
+        dst.write_target(self.max_tile)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let tiles = src.read_target_vec()?;
+        let max_tile = src.read_target()?;
+        Ok(Self { tiles, max_tile })
+    }
+}
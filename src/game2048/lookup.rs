@@ -0,0 +1,221 @@
+//! Lookup table variant of the move circuit.
+//!
+//! Each line costs two lookups instead of the compaction and merge gadgets, but the
+//! 2^16-entry merge and overflow tables take about 2500 rows each. For a single move that
+//! is far larger than the arithmetic circuit (degree_bits 12 vs 8, see the
+//! `game2048_merge_strategy` benchmark); it only pays off once many moves share the table.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use plonky2::field::extension::Extendable;
+use plonky2::field::types::Field;
+use plonky2::gates::lookup_table::LookupTable;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitConfig, CommonCircuitData};
+use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+
+use super::{Game2048Circuit, Game2048Engine, Game2048Targets, D, F};
+
+/// Largest tile exponent the lookup circuit can represent (2^15 = 32768), so that
+/// a row of four exponents packs into the u16 domain of a plonky2 lookup table
+pub const MAX_LOOKUP_EXPONENT: u32 = 15;
+
+impl Game2048Circuit {
+    /// Build the move circuit with lookup tables in place of the arithmetic merge.
+    ///
+    /// Tiles are converted to exponents, each line is packed into a 16-bit value
+    /// and merged by a single lookup. Public inputs are the same as `build_circuit`,
+    /// but tiles are limited to 2^MAX_LOOKUP_EXPONENT and may not merge past it.
+    pub fn build_lookup_circuit() -> (CircuitBuilder<F, D>, Game2048Targets) {
+        let config = CircuitConfig::standard_recursion_config();
+        let mut builder = CircuitBuilder::<F, D>::new(config);
+
+        let targets = Self::add_move_targets(&mut builder);
+
+        Self::add_lookup_constraints(
+            &mut builder,
+            &targets.before_board,
+            &targets.after_board,
+            targets.direction,
+        );
+
+        (builder, targets)
+    }
+
+    /// Add lookup based constraints for the move in the selected direction
+    fn add_lookup_constraints(
+        builder: &mut CircuitBuilder<F, D>,
+        before_board: &[Target],
+        after_board: &[Target],
+        direction_target: Target,
+    ) {
+        let exponent_lut = builder.add_lookup_table_from_pairs(exponent_table());
+        let merge_lut = builder.add_lookup_table_from_pairs(merge_table());
+        let overflow_lut = builder.add_lookup_table_from_pairs(overflow_table());
+
+        let before_exponents: Vec<_> = before_board
+            .iter()
+            .map(|&tile| Self::tile_exponent(builder, tile, exponent_lut))
+            .collect();
+        let after_exponents: Vec<_> = after_board
+            .iter()
+            .map(|&tile| Self::tile_exponent(builder, tile, exponent_lut))
+            .collect();
+
+        let direction_index = Self::direction_index(builder, direction_target);
+        let before_rows = Self::orient_board(builder, &before_exponents, direction_index);
+        let after_rows = Self::orient_board(builder, &after_exponents, direction_index);
+
+        for (before_row, after_row) in before_rows.into_iter().zip(after_rows) {
+            let packed_before = Self::pack_exponents(builder, before_row);
+            let packed_after = Self::pack_exponents(builder, after_row);
+            let merged = builder.add_lookup_from_index(packed_before, merge_lut);
+            builder.connect(merged, packed_after);
+
+            // Every packed value is some after line, so overflow has its own table
+            let overflows = builder.add_lookup_from_index(packed_before, overflow_lut);
+            builder.assert_zero(overflows);
+        }
+    }
+
+    /// Exponent of a tile (0 for an empty cell), constrained by looking the tile back up
+    fn tile_exponent(builder: &mut CircuitBuilder<F, D>, tile: Target, exponent_lut: usize) -> Target {
+        let exponent = Self::add_exponent_hint(builder, tile, MAX_LOOKUP_EXPONENT);
+
+        // The table only holds exponents 0..=MAX_LOOKUP_EXPONENT, which also range checks them
+        let looked_up_tile = builder.add_lookup_from_index(exponent, exponent_lut);
+        builder.connect(looked_up_tile, tile);
+        exponent
+    }
+
+    /// Unconstrained exponent of a tile (0 for an empty cell), for a tile that is 0 or
+    /// a power of two from 2 to 2^max_exponent; witness generation fails on any other tile
+    pub(crate) fn add_exponent_hint(builder: &mut CircuitBuilder<F, D>, tile: Target, max_exponent: u32) -> Target {
+        let exponent = builder.add_virtual_target();
+        builder.add_simple_generator(TileExponentGenerator { tile, exponent, max_exponent });
+        exponent
+    }
+
+    /// Pack four 4-bit exponents into e0 + 16*e1 + 256*e2 + 4096*e3
+    fn pack_exponents(builder: &mut CircuitBuilder<F, D>, exponents: [Target; 4]) -> Target {
+        let sixteen = builder.constant(F::from_canonical_u32(16));
+        let mut packed = exponents[3];
+        for &exponent in exponents[..3].iter().rev() {
+            packed = builder.mul_add(packed, sixteen, exponent);
+        }
+        packed
+    }
+}
+
+pub(crate) fn tile_from_exponent(exponent: u32) -> u64 {
+    if exponent == 0 {
+        0
+    } else {
+        1 << exponent
+    }
+}
+
+fn exponent_of(tile: u64) -> u32 {
+    if tile == 0 {
+        0
+    } else {
+        tile.trailing_zeros()
+    }
+}
+
+/// (exponent, tile) pairs for every representable tile
+fn exponent_table() -> LookupTable {
+    Arc::new(
+        (0..=MAX_LOOKUP_EXPONENT)
+            .map(|exponent| (exponent as u16, tile_from_exponent(exponent) as u16))
+            .collect(),
+    )
+}
+
+/// Tiles of a packed line
+fn unpack_line(packed: u16) -> [u64; 4] {
+    [0, 4, 8, 12].map(|shift| tile_from_exponent(((packed >> shift) & 0xf) as u32))
+}
+
+/// Whether merging a line would create a tile past 2^MAX_LOOKUP_EXPONENT
+fn merge_overflows(row: [u64; 4]) -> bool {
+    let max_tile = tile_from_exponent(MAX_LOOKUP_EXPONENT);
+    Game2048Engine::merge_row(row).iter().any(|&tile| tile > max_tile)
+}
+
+/// (packed line, packed merged line) pairs for every packed line, in input order.
+///
+/// Every line needs an entry, in order, since plonky2's lookup generator indexes the
+/// table by input value and panics on inputs past its end. Lines whose merge would
+/// overflow map to 0, and are rejected by `overflow_table` instead: every 16-bit
+/// value is the packed form of some after line, so no output could reject them here.
+fn merge_table() -> LookupTable {
+    Arc::new(
+        (0..=u16::MAX)
+            .map(|packed| {
+                let row = unpack_line(packed);
+                if merge_overflows(row) {
+                    return (packed, 0);
+                }
+                let packed_merged = Game2048Engine::merge_row(row)
+                    .iter()
+                    .rev()
+                    .fold(0u16, |acc, &tile| (acc << 4) | exponent_of(tile) as u16);
+                (packed, packed_merged)
+            })
+            .collect(),
+    )
+}
+
+/// (packed line, 1 if its merge would overflow else 0) pairs for every packed line, in input order
+fn overflow_table() -> LookupTable {
+    Arc::new(
+        (0..=u16::MAX)
+            .map(|packed| (packed, merge_overflows(unpack_line(packed)) as u16))
+            .collect(),
+    )
+}
+
+/// Computes the exponent of a tile so it can be checked against a table of tiles
+#[derive(Debug, Default)]
+struct TileExponentGenerator {
+    tile: Target,
+    exponent: Target,
+    max_exponent: u32,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for TileExponentGenerator {
+    fn id(&self) -> String {
+        "TileExponentGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.tile]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> Result<()> {
+        let tile = witness.get_target(self.tile).to_canonical_u64();
+        let exponent = (0..=self.max_exponent)
+            .find(|&exponent| tile_from_exponent(exponent) == tile)
+            .ok_or_else(|| anyhow!("Tile {} is not a power of two up to 2^{}", tile, self.max_exponent))?;
+        out_buffer.set_target(self.exponent, F::from_canonical_u32(exponent))
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target(self.tile)?;
+        dst.write_target(self.exponent)?;
+        dst.write_u32(self.max_exponent)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let tile = src.read_target()?;
+        let exponent = src.read_target()?;
+        let max_exponent = src.read_u32()?;
+        Ok(Self { tile, exponent, max_exponent })
+    }
+}
@@ -1,172 +1,259 @@
 use plonky2::field::goldilocks_field::GoldilocksField;
 use plonky2::field::types::Field;
-use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
 use plonky2::plonk::circuit_builder::CircuitBuilder;
-use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+
+mod commitment;
+mod compress;
+mod cube;
+mod engine;
+mod error;
+mod fibonacci;
+mod game;
+mod history;
+mod identity;
+mod leaderboard;
+mod lookup;
+mod obstacles;
+mod profile;
+mod prover;
+mod range;
+mod rules;
+mod seed;
+mod spawn;
+mod stats;
+mod threes;
+mod trace;
+
+pub use commitment::{CommittedTraceProof, CommittedTraceProver, CommittedTraceTargets, Salt};
+pub use compress::{CompressedProof, Game2048Compressor};
+pub use cube::{Cube, CubeCellMismatch, CubeDirection, CubeMoveProof, CubeProver, CubeTargets};
+pub use engine::{Board, CellMismatch, Direction, Game2048Engine, Line};
+pub use error::Game2048Error;
+pub use fibonacci::{FibonacciRules, MAX_FIBONACCI_TILE};
+pub use game::{Game2048GameProof, Game2048GameProver, GameTargets, OpeningTargets, TimedMove, OPENING_TILES};
+pub use history::{BoardHistory, HistoryInclusion, NO_DIRECTION};
+pub use identity::{IdentityTargets, PlayerIdentity, IDENTITY_LEN};
+pub use leaderboard::{LeaderboardStats, LeaderboardTargets, LEADERBOARD_LEN};
+pub use lookup::MAX_LOOKUP_EXPONENT;
+pub use obstacles::{ObstacleMask, ObstacleMoveProof, ObstacleMoveTargets, ObstacleProver};
+pub use profile::CircuitProfile;
+pub use prover::{Game2048Proof, Game2048Prover, Game2048Verifier, HashOutput};
+pub use rules::{ClassicRules, MergedRow, RuleSet, MAX_TILE_EXPONENT};
+pub use seed::{PlayerEntropy, SeedTargets, ServerSeed};
+pub use spawn::SpawnMoveTargets;
+pub use stats::CircuitStats;
+pub use threes::{ThreesRules, MAX_THREES_TILE};
+pub use trace::{Game2048TraceProof, Game2048TraceProver, TraceTargets};
 
 pub const D: usize = 2;
 pub type F = GoldilocksField;
+pub type C = PoseidonGoldilocksConfig;
+
+/// Targets for the public inputs of a move
+#[derive(Clone, Copy, Debug)]
+pub struct Game2048Targets {
+    pub before_board: [Target; 16],
+    pub after_board: [Target; 16],
+    pub direction: Target,
+}
+
+impl Game2048Targets {
+    /// Assign a move to the targets
+    pub fn set_witness(
+        &self,
+        pw: &mut PartialWitness<F>,
+        before_board: &Board,
+        after_board: &Board,
+        direction: Direction,
+    ) -> Result<(), Game2048Error> {
+        for (&target, &tile) in self.before_board.iter().zip(before_board) {
+            pw.set_target(target, F::from_canonical_u64(tile))
+                .map_err(Game2048Error::WitnessConflict)?;
+        }
+        for (&target, &tile) in self.after_board.iter().zip(after_board) {
+            pw.set_target(target, F::from_canonical_u64(tile))
+                .map_err(Game2048Error::WitnessConflict)?;
+        }
+        pw.set_target(self.direction, F::from_canonical_u64(direction.as_u64()))
+            .map_err(Game2048Error::WitnessConflict)
+    }
+}
 
 pub struct Game2048Circuit;
 
 impl Game2048Circuit {
     /// Build the circuit for validating a 2048 game move
-    pub fn build_circuit() -> (CircuitBuilder<F, D>, Vec<Target>) {
-        let config = CircuitConfig::standard_recursion_config();
-        let mut builder = CircuitBuilder::<F, D>::new(config);
+    pub fn build_circuit() -> (CircuitBuilder<F, D>, Game2048Targets) {
+        Self::build_circuit_with_profile(CircuitProfile::default())
+    }
 
-        // Create targets for before_board, after_board, and direction
-        let before_board_targets: Vec<_> = (0..16).map(|_| builder.add_virtual_target()).collect();
-        let after_board_targets: Vec<_> = (0..16).map(|_| builder.add_virtual_target()).collect();
-        let direction_target = builder.add_virtual_target();
+    /// Build the move circuit with the configuration of the given profile
+    pub fn build_circuit_with_profile(profile: CircuitProfile) -> (CircuitBuilder<F, D>, Game2048Targets) {
+        Self::build_circuit_with_rules(profile, &ClassicRules)
+    }
 
-        // Register public inputs for before_board, after_board, and direction
-        for &target in &before_board_targets {
-            builder.register_public_input(target);
-        }
-        for &target in &after_board_targets {
-            builder.register_public_input(target);
-        }
-        builder.register_public_input(direction_target);
+    /// Build the move circuit for a 2048 variant
+    pub fn build_circuit_with_rules(
+        profile: CircuitProfile,
+        rules: &dyn RuleSet,
+    ) -> (CircuitBuilder<F, D>, Game2048Targets) {
+        let mut builder = CircuitBuilder::<F, D>::new(profile.config());
 
-        // Add constraints for each move direction
-        Self::add_constraints(
+        let targets = Self::add_move_targets(&mut builder);
+
+        // Add constraints for the selected move direction
+        Self::add_rule_constraints(
             &mut builder,
-            &before_board_targets,
-            &after_board_targets,
-            direction_target,
+            rules,
+            &targets.before_board,
+            &targets.after_board,
+            targets.direction,
         );
 
-        // Combine all targets into a single vector
-        let mut targets = before_board_targets;
-        targets.extend(after_board_targets);
-        targets.push(direction_target);
-
         (builder, targets)
     }
 
-    /// Add constraints dynamically based on the move direction
+    /// Create and register the public inputs of a move: before_board, after_board and direction
+    fn add_move_targets(builder: &mut CircuitBuilder<F, D>) -> Game2048Targets {
+        // Create targets for before_board, after_board, and direction
+        let before_board = builder.add_virtual_target_arr::<16>();
+        let after_board = builder.add_virtual_target_arr::<16>();
+        let direction = builder.add_virtual_target();
+
+        // Register public inputs for before_board, after_board, and direction
+        builder.register_public_inputs(&before_board);
+        builder.register_public_inputs(&after_board);
+        builder.register_public_input(direction);
+
+        Game2048Targets { before_board, after_board, direction }
+    }
+
+    /// Index of the selected direction, recomputed from equality flags
+    ///
+    /// Using this rather than the raw direction as a random access index keeps witness
+    /// generation in range, so an invalid direction fails to prove instead of panicking.
+    fn direction_index(builder: &mut CircuitBuilder<F, D>, direction_target: Target) -> Target {
+        Self::range_checked_index(builder, direction_target, Direction::ALL.len())
+    }
+
+    /// A value that must be one of 0..count, recomputed from equality flags
+    fn range_checked_index(builder: &mut CircuitBuilder<F, D>, value: Target, count: usize) -> Target {
+        // Boolean flags for each allowed value
+        let flags: Vec<_> = (0..count)
+            .map(|allowed| {
+                let allowed = builder.constant(F::from_canonical_usize(allowed));
+                builder.is_equal(value, allowed)
+            })
+            .collect();
+
+        // Exactly one flag must be set, otherwise a value outside 0..count would
+        // select index 0
+        let flag_sum = builder.add_many(flags.iter().map(|flag| flag.target));
+        builder.assert_one(flag_sum);
+
+        let mut index = builder.zero();
+        for (allowed, flag) in flags.into_iter().enumerate() {
+            index = builder.mul_const_add(F::from_canonical_usize(allowed), flag.target, index);
+        }
+        index
+    }
+
+    /// Add constraints for the move in the selected direction
+    ///
+    /// Both boards are first rearranged so that the selected direction becomes a move
+    /// to the left, so a single set of four row merges covers every direction.
     fn add_constraints(
         builder: &mut CircuitBuilder<F, D>,
         before_board: &[Target],
         after_board: &[Target],
         direction_target: Target,
     ) {
-        // Constants for move directions
-        let up_const = builder.constant(F::from_canonical_u32(0));
-        let down_const = builder.constant(F::from_canonical_u32(1));
-        let left_const = builder.constant(F::from_canonical_u32(2));
-        let right_const = builder.constant(F::from_canonical_u32(3));
-
-        // Boolean flags for each direction
-        let is_up = builder.is_equal(direction_target, up_const);
-        let is_down = builder.is_equal(direction_target, down_const);
-        let is_left = builder.is_equal(direction_target, left_const);
-        let is_right = builder.is_equal(direction_target, right_const);
-
-        // Add constraints for each direction conditionally
-        Self::add_constraints_up(builder, before_board, after_board, is_up);
-        Self::add_constraints_down(builder, before_board, after_board, is_down);
-        Self::add_constraints_left(builder, before_board, after_board, is_left);
-        Self::add_constraints_right(builder, before_board, after_board, is_right);
+        Self::add_rule_constraints(builder, &ClassicRules, before_board, after_board, direction_target);
     }
 
-    /// Add constraints for "up" direction
-    fn add_constraints_up(
+    /// `add_constraints` under the given rules, returning the points scored by the move
+    pub(crate) fn add_rule_constraints(
         builder: &mut CircuitBuilder<F, D>,
+        rules: &dyn RuleSet,
         before_board: &[Target],
         after_board: &[Target],
-        condition: BoolTarget,
-    ) {
-        for col in 0..4 {
-            let before_col: Vec<_> = (0..4).map(|row| before_board[row * 4 + col]).collect();
-            let after_col: Vec<_> = (0..4).map(|row| after_board[row * 4 + col]).collect();
-            Self::validate_tiles(builder, &before_col, &after_col, condition);
+        direction_target: Target,
+    ) -> Target {
+        for &tile in before_board.iter().chain(after_board) {
+            rules.add_tile_check(builder, tile);
         }
-    }
-
-    /// Add constraints for "down" direction
-    fn add_constraints_down(
-        builder: &mut CircuitBuilder<F, D>,
-        before_board: &[Target],
-        after_board: &[Target],
-        condition: BoolTarget,
-    ) {
-        for col in 0..4 {
-            let before_col: Vec<_> = (0..4).map(|row| before_board[row * 4 + col]).collect();
-            let mut reversed_before_col = before_col.clone();
-            reversed_before_col.reverse();
 
-            let after_col: Vec<_> = (0..4).map(|row| after_board[row * 4 + col]).collect();
-            let mut reversed_after_col = after_col.clone();
-            reversed_after_col.reverse();
+        let direction_index = Self::direction_index(builder, direction_target);
+        let before_rows = Self::orient_board(builder, before_board, direction_index);
+        let after_rows = Self::orient_board(builder, after_board, direction_index);
 
-            Self::validate_tiles(builder, &reversed_before_col, &reversed_after_col, condition);
-        }
+        let scores: Vec<_> = before_rows
+            .iter()
+            .zip(&after_rows)
+            .map(|(before_row, after_row)| Self::validate_tiles(builder, rules, before_row, after_row))
+            .collect();
+        builder.add_many(scores)
     }
 
-    /// Add constraints for "left" direction
-    fn add_constraints_left(
-        builder: &mut CircuitBuilder<F, D>,
-        before_board: &[Target],
-        after_board: &[Target],
-        condition: BoolTarget,
-    ) {
-        for row in 0..4 {
-            let before_row: Vec<_> = (0..4).map(|col| before_board[row * 4 + col]).collect();
-            let after_row: Vec<_> = (0..4).map(|col| after_board[row * 4 + col]).collect();
-            Self::validate_tiles(builder, &before_row, &after_row, condition);
-        }
+    /// Rearrange a board into the four lines of the selected direction, each ordered
+    /// so that tiles slide toward index 0
+    ///
+    /// Every cell is a random access over its source cell for up, down, left and right.
+    fn orient_board(builder: &mut CircuitBuilder<F, D>, board: &[Target], direction_index: Target) -> [[Target; 4]; 4] {
+        Self::orient_lines(builder, board, &Direction::ALL.map(Game2048Engine::lines), direction_index)
     }
 
-    /// Add constraints for "right" direction
-    fn add_constraints_right(
+    /// Rearrange cells into the lines of the selected direction, given the lines of
+    /// every direction
+    fn orient_lines<const LINES: usize>(
         builder: &mut CircuitBuilder<F, D>,
-        before_board: &[Target],
-        after_board: &[Target],
-        condition: BoolTarget,
-    ) {
-        for row in 0..4 {
-            let before_row: Vec<_> = (0..4).map(|col| before_board[row * 4 + col]).collect();
-            let mut reversed_before_row = before_row.clone();
-            reversed_before_row.reverse();
-
-            let after_row: Vec<_> = (0..4).map(|col| after_board[row * 4 + col]).collect();
-            let mut reversed_after_row = after_row.clone();
-            reversed_after_row.reverse();
-
-            Self::validate_tiles(builder, &reversed_before_row, &reversed_after_row, condition);
+        cells: &[Target],
+        lines_by_direction: &[[[usize; 4]; LINES]],
+        direction_index: Target,
+    ) -> [[Target; 4]; LINES] {
+        let mut rows = [[direction_index; 4]; LINES];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, tile) in row.iter_mut().enumerate() {
+                let candidates = lines_by_direction.iter().map(|lines| cells[lines[i][j]]).collect();
+                *tile = builder.random_access(direction_index, candidates);
+            }
         }
+        rows
     }
 
-    /// Validate the tiles for a single row or column
+    /// Validate the tiles for a single row or column, returning the points it scored
     fn validate_tiles(
         builder: &mut CircuitBuilder<F, D>,
-        before_tiles: &[Target],
-        after_tiles: &[Target],
-        condition: BoolTarget,
-    ) {
-        let merged = Self::merge_2048_row(builder, before_tiles[0], before_tiles[1], before_tiles[2], before_tiles[3]);
-
-        let out0 = builder._if(condition, merged[0], after_tiles[0]);
-        let out1 = builder._if(condition, merged[1], after_tiles[1]);
-        let out2 = builder._if(condition, merged[2], after_tiles[2]);
-        let out3 = builder._if(condition, merged[3], after_tiles[3]);
+        rules: &dyn RuleSet,
+        before_tiles: &[Target; 4],
+        after_tiles: &[Target; 4],
+    ) -> Target {
+        let merged = rules.add_merge_row(builder, *before_tiles);
 
-        let final_row = [out0, out1, out2, out3];
-
-        // builder.connect_array(merged, after_tiles);
-        for (&merged_tile, &after_tile) in final_row.iter().zip(after_tiles) {
+        for (&merged_tile, &after_tile) in merged.tiles.iter().zip(after_tiles) {
             builder.connect(merged_tile, after_tile);
         }
+        merged.score
     }
 
     /// Merge a single 2048 row [a,b,c,d] toward the left
     pub fn merge_2048_row(builder: &mut CircuitBuilder<F, D>, a: Target, b: Target, c: Target, d: Target) -> [Target; 4] {
+        ClassicRules.add_merge_row(builder, [a, b, c, d]).tiles
+    }
+
+    /// Compact a row toward index 0, then merge neighbours once each from index 0 under the given rules
+    pub(crate) fn add_compact_merge<R: RuleSet + ?Sized>(
+        builder: &mut CircuitBuilder<F, D>,
+        rules: &R,
+        row: [Target; 4],
+    ) -> MergedRow {
         let zero = builder.zero();
 
         // // Step 1: Compact nonzero tiles to the left
-        let result = Self::shift_nonzero_left(builder, a, b, c, d, zero);
+        let result = Self::shift_nonzero_left(builder, row[0], row[1], row[2], row[3], zero);
         let x0 = result[0];
         let x1 = result[1];
         let x2 = result[2];
@@ -175,88 +262,50 @@ impl Game2048Circuit {
         // Step 2: Merge logic
         // Merge from left:
         // Check (x0,x1)
-        let eq_x0_x1 = builder.is_equal(x0,x1);
-        let x0_eq_zero = builder.is_equal(x0,zero);
-        let x1_eq_zero = builder.is_equal(x1,zero);
-        let x0_nonzero = builder.not(x0_eq_zero);
-        let x1_nonzero = builder.not(x1_eq_zero);
-        let can_merge_x0_x1_pre = builder.and(eq_x0_x1, x0_nonzero);
-        let can_merge_x0_x1 = builder.and(can_merge_x0_x1_pre, x1_nonzero);
+        let can_merge_x0_x1 = rules.add_can_merge(builder, x0, x1);
 
         // Merge (x0,x1) if possible
-        let two = builder.constant(F::from_canonical_u64(2));
-        let doubled_x0 = builder.mul(x0,two);
+        let merged_x0 = rules.add_merged_value(builder, x0, x1);
+        let score_x0 = rules.add_merge_score(builder, x0, x1, merged_x0);
+        let mut score = builder.mul(can_merge_x0_x1.target, score_x0);
 
-        let nx0 = builder._if(can_merge_x0_x1, doubled_x0, x0);
+        let nx0 = builder._if(can_merge_x0_x1, merged_x0, x0);
         let mut nx1 = builder._if(can_merge_x0_x1, x2, x1);
         let mut nx2 = builder._if(can_merge_x0_x1, x3, x2);
         let mut nx3 = builder._if(can_merge_x0_x1, zero, x3);
 
         let not_merged_x0_x1 = builder.not(can_merge_x0_x1);
 
-        // If merged at (x0,x1), we skip checking (x1,x2) and go directly to (x2,x3).
-        // If not merged at (x0,x1), we check (x1,x2).
-
-        // Check (x1,x2) only if not merged at (x0,x1)
-        let eq_x1_x2 = builder.is_equal(nx1,nx2);
-        let x1_eq_zero2 = builder.is_equal(nx1,zero);
-        let x2_eq_zero2 = builder.is_equal(nx2,zero);
-        let x1_nonzero2 = builder.not(x1_eq_zero2);
-        let x2_nonzero2 = builder.not(x2_eq_zero2);
-        let can_merge_x1_x2_pre = builder.and(eq_x1_x2, x1_nonzero2);
-        let can_merge_x1_x2 = builder.and(can_merge_x1_x2_pre, x2_nonzero2);
+        // If merged at (x0,x1), the row shifted left so (nx1,nx2) now holds the original (x2,x3).
+        // Either way, (nx1,nx2) is the next pair that may merge.
 
-        let do_x1_x2_merge = builder.and(not_merged_x0_x1, can_merge_x1_x2);
+        // Check (nx1,nx2)
+        let do_x1_x2_merge = rules.add_can_merge(builder, nx1, nx2);
 
-        let doubled_x1 = builder.mul(nx1,two);
-        nx1 = builder._if(do_x1_x2_merge, doubled_x1, nx1);
+        let merged_x1 = rules.add_merged_value(builder, nx1, nx2);
+        let score_x1 = rules.add_merge_score(builder, nx1, nx2, merged_x1);
+        score = builder.mul_add(do_x1_x2_merge.target, score_x1, score);
+        nx1 = builder._if(do_x1_x2_merge, merged_x1, nx1);
         nx2 = builder._if(do_x1_x2_merge, nx3, nx2);
         nx3 = builder._if(do_x1_x2_merge, zero, nx3);
 
-        // If merged at (x1,x2), skip (x2,x3).
-        // If merged at (x0,x1), we want to check (x2,x3).
-        // If not merged at (x0,x1) and not merged at (x1,x2), we check (x2,x3).
-
+        // (x2,x3) can only merge if nothing merged so far. Any earlier merge
+        // shifted a zero into nx3, so there is nothing left to pair with.
         let not_merged_x1_x2 = builder.not(do_x1_x2_merge);
-
-        // Conditions for checking (x2,x3):
-        // - If merged at (x0,x1), check (x2,x3).
-        // - If not merged at (x0,x1) and not merged at (x1,x2), check (x2,x3).
-        // - If merged at (x1,x2), do NOT check (x2,x3).
-
-        // This ensures that if we merged at x0,x1 or no merges happened so far, we still check x2,x3. 
-        // If we merged at x1,x2, not_merged_x1_x2=0 and can_merge_x0_x1=0, 
-        // so check_x2_x3=0 => no check.
-
-        // Actually, refine logic for check_x2_x3:
-        // - Merged at (x0,x1): can_merge_x0_x1=1 => check_x2_x3=1
-        // - Not merged at (x0,x1) (means can_merge_x0_x1=0):
-        //    If merged at (x1,x2) (do_x1_x2_merge=1) => no check_x2_x3=0
-        //    Else no merges (do_x1_x2_merge=0) => check_x2_x3=1
-        //
-        // We can achieve this by:
-        // check_x2_x3 = can_merge_x0_x1 OR (not_merged_x0_x1 AND not_merged_x1_x2)
-        let not_merged_x0_x1_and_not_x1_x2 = builder.and(not_merged_x0_x1, not_merged_x1_x2);
-        let check_x2_x3_final = builder.or(can_merge_x0_x1, not_merged_x0_x1_and_not_x1_x2);
+        let check_x2_x3_final = builder.and(not_merged_x0_x1, not_merged_x1_x2);
 
         // Check (x2,x3) if allowed
-        let eq_x2_x3 = builder.is_equal(nx2,nx3);
-        let x2_eq_zero3 = builder.is_equal(nx2,zero);
-        let x3_eq_zero3 = builder.is_equal(nx3,zero);
-        let x2_nonzero3 = builder.not(x2_eq_zero3);
-        let x3_nonzero3 = builder.not(x3_eq_zero3);
-        let can_merge_x2_x3_pre = builder.and(eq_x2_x3, x2_nonzero3);
-        let can_merge_x2_x3 = builder.and(can_merge_x2_x3_pre, x3_nonzero3);
-
-        let do_x2_x3_merge_pre = builder.and(check_x2_x3_final, can_merge_x2_x3);
-        let do_x2_x3_merge = do_x2_x3_merge_pre;
-
-        let doubled_x2 = builder.mul(nx2,two);
-        nx2 = builder._if(do_x2_x3_merge, doubled_x2, nx2);
+        let can_merge_x2_x3 = rules.add_can_merge(builder, nx2, nx3);
+        let do_x2_x3_merge = builder.and(check_x2_x3_final, can_merge_x2_x3);
+
+        let merged_x2 = rules.add_merged_value(builder, nx2, nx3);
+        let score_x2 = rules.add_merge_score(builder, nx2, nx3, merged_x2);
+        score = builder.mul_add(do_x2_x3_merge.target, score_x2, score);
+        nx2 = builder._if(do_x2_x3_merge, merged_x2, nx2);
         nx3 = builder._if(do_x2_x3_merge, zero, nx3);
 
-        // Now [nx0, nx1, nx2, nx3] is fully merged according to 2048 rules.
-        [nx0, nx1, nx2, nx3]
+        // Now [nx0, nx1, nx2, nx3] is fully merged according to the rules.
+        MergedRow { tiles: [nx0, nx1, nx2, nx3], score }
     }
 
     /// A simplified helper that picks remaining two tiles after x0 and x1.
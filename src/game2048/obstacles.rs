@@ -0,0 +1,243 @@
+//! Obstacle boards: immovable walls on some cells, given as a public mask.
+//!
+//! A wall holds no tile and splits its line into runs of cells. Tiles slide and merge
+//! within their run as in a line of their own, toward the run's end nearest the edge
+//! the move goes to, and never cross a wall.
+
+use plonky2::field::types::{Field, PrimeField64};
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{ProverCircuitData, VerifierCircuitData};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+
+use super::{
+    Board, CircuitProfile, ClassicRules, Direction, Game2048Circuit, Game2048Engine, Game2048Error, Game2048Targets,
+    RuleSet, C, D, F,
+};
+
+/// Cells holding a wall, row-major
+pub type ObstacleMask = [bool; 16];
+
+impl Game2048Engine {
+    /// Merge a single line toward index 0, separately within each run between walls
+    pub fn merge_row_with_walls(row: [u64; 4], walls: [bool; 4]) -> [u64; 4] {
+        ClassicRules.merge_row_with_walls(row, walls)
+    }
+
+    /// Apply a move to a board with walls
+    pub fn apply_move_with_obstacles(board: &Board, obstacles: &ObstacleMask, direction: Direction) -> Board {
+        ClassicRules.apply_move_with_obstacles(board, obstacles, direction)
+    }
+
+    /// `check_move` on a board with walls, which must not hold tiles
+    pub fn check_move_with_obstacles(
+        before_board: &Board,
+        after_board: &Board,
+        obstacles: &ObstacleMask,
+        direction: Direction,
+    ) -> Result<(), Game2048Error> {
+        ClassicRules.check_move_with_obstacles(before_board, after_board, obstacles, direction)
+    }
+}
+
+/// Targets of a move on a board with walls
+#[derive(Clone, Copy, Debug)]
+pub struct ObstacleMoveTargets {
+    pub game: Game2048Targets,
+    pub obstacles: [BoolTarget; 16],
+}
+
+impl ObstacleMoveTargets {
+    /// Assign a move and the walls of its board
+    pub fn set_witness(
+        &self,
+        pw: &mut PartialWitness<F>,
+        before_board: &Board,
+        after_board: &Board,
+        obstacles: &ObstacleMask,
+        direction: Direction,
+    ) -> Result<(), Game2048Error> {
+        self.game.set_witness(pw, before_board, after_board, direction)?;
+        for (&target, &wall) in self.obstacles.iter().zip(obstacles) {
+            pw.set_bool_target(target, wall).map_err(Game2048Error::WitnessConflict)?;
+        }
+        Ok(())
+    }
+}
+
+impl Game2048Circuit {
+    /// Build the move circuit for a board with walls
+    ///
+    /// Public inputs are before_board (16), after_board (16), direction (1) and the
+    /// obstacle mask (16), 1 for a wall.
+    pub fn build_obstacle_circuit(profile: CircuitProfile) -> (CircuitBuilder<F, D>, ObstacleMoveTargets) {
+        Self::build_obstacle_circuit_with_rules(profile, &ClassicRules)
+    }
+
+    /// Build the obstacle circuit for a 2048 variant, whose rules merge the runs between walls
+    pub fn build_obstacle_circuit_with_rules(
+        profile: CircuitProfile,
+        rules: &dyn RuleSet,
+    ) -> (CircuitBuilder<F, D>, ObstacleMoveTargets) {
+        let mut builder = CircuitBuilder::<F, D>::new(profile.config());
+        let game = Self::add_move_targets(&mut builder);
+        let obstacles = [(); 16].map(|_| builder.add_virtual_bool_target_safe());
+        builder.register_public_inputs(&obstacles.map(|wall| wall.target));
+
+        for &tile in game.before_board.iter().chain(&game.after_board) {
+            rules.add_tile_check(&mut builder, tile);
+        }
+
+        // Walls hold no tile before the move, and the merge leaves them empty after it
+        for (wall, &tile) in obstacles.iter().zip(&game.before_board) {
+            let tile_on_wall = builder.mul(wall.target, tile);
+            builder.assert_zero(tile_on_wall);
+        }
+
+        let direction_index = Self::direction_index(&mut builder, game.direction);
+        let before_rows = Self::orient_board(&mut builder, &game.before_board, direction_index);
+        let after_rows = Self::orient_board(&mut builder, &game.after_board, direction_index);
+        let wall_rows = Self::orient_board(&mut builder, &obstacles.map(|wall| wall.target), direction_index);
+        for ((before_row, after_row), walls) in before_rows.iter().zip(&after_rows).zip(&wall_rows) {
+            let merged = Self::add_walled_merge(&mut builder, rules, *before_row, *walls);
+            for (merged_tile, &after_tile) in merged.into_iter().zip(after_row) {
+                builder.connect(merged_tile, after_tile);
+            }
+        }
+
+        (builder, ObstacleMoveTargets { game, obstacles })
+    }
+
+    /// In-circuit `merge_row_with_walls`, for walls that are 0 or 1
+    ///
+    /// Every run of cells is merged once, and each cell then selects its value from the
+    /// run it belongs to under the row's wall pattern.
+    ///
+    /// That is 10 row merges per line where a plain move has 1, so the four lines make
+    /// up most of the obstacle circuit: about 340 gates and degree_bits 9, against about
+    /// 60 gates and degree_bits 6 for `build_circuit`.
+    fn add_walled_merge(
+        builder: &mut CircuitBuilder<F, D>,
+        rules: &dyn RuleSet,
+        row: [Target; 4],
+        walls: [Target; 4],
+    ) -> [Target; 4] {
+        let zero = builder.zero();
+
+        // runs[start][end]: the cells start..end merged as a line of their own
+        let mut runs = [[[zero; 4]; 5]; 4];
+        for start in 0..4 {
+            for end in start + 1..=4 {
+                let mut run = [zero; 4];
+                run[..end - start].copy_from_slice(&row[start..end]);
+                runs[start][end] = rules.add_merge_row(builder, run).tiles;
+            }
+        }
+
+        let mut pattern = zero;
+        for &wall in walls.iter().rev() {
+            pattern = builder.mul_const_add(F::TWO, pattern, wall);
+        }
+
+        let mut merged = [zero; 4];
+        for (cell, tile) in merged.iter_mut().enumerate() {
+            let candidates = (0..16)
+                .map(|pattern: usize| {
+                    let is_wall = |cell: usize| pattern >> cell & 1 == 1;
+                    if is_wall(cell) {
+                        return zero;
+                    }
+                    let start = (0..cell).rev().find(|&other| is_wall(other)).map_or(0, |wall| wall + 1);
+                    let end = (cell + 1..4).find(|&other| is_wall(other)).unwrap_or(4);
+                    runs[start][end][cell - start]
+                })
+                .collect();
+            *tile = builder.random_access(pattern, candidates);
+        }
+        merged
+    }
+}
+
+/// A proof of a move on a board with walls, with the boards, the direction and the
+/// obstacle mask as public inputs
+#[derive(Clone, Debug)]
+pub struct ObstacleMoveProof {
+    pub proof: ProofWithPublicInputs<F, C, D>,
+}
+
+impl ObstacleMoveProof {
+    fn public_tiles(&self, offset: usize) -> Board {
+        let mut board = [0; 16];
+        for (tile, input) in board.iter_mut().zip(&self.proof.public_inputs[offset..offset + 16]) {
+            *tile = input.to_canonical_u64();
+        }
+        board
+    }
+
+    pub fn before_board(&self) -> Board {
+        self.public_tiles(0)
+    }
+
+    pub fn after_board(&self) -> Board {
+        self.public_tiles(16)
+    }
+
+    pub fn direction(&self) -> Option<Direction> {
+        Direction::from_u64(self.proof.public_inputs[32].to_canonical_u64())
+    }
+
+    pub fn obstacles(&self) -> ObstacleMask {
+        std::array::from_fn(|cell| self.proof.public_inputs[33 + cell] == F::ONE)
+    }
+}
+
+/// Proves moves on boards with walls, with a circuit that is built once
+pub struct ObstacleProver {
+    circuit: ProverCircuitData<F, C, D>,
+    verifier: VerifierCircuitData<F, C, D>,
+    targets: ObstacleMoveTargets,
+    rules: Box<dyn RuleSet>,
+}
+
+impl ObstacleProver {
+    pub fn new(profile: CircuitProfile) -> Self {
+        Self::with_rules(profile, ClassicRules)
+    }
+
+    /// Prover for moves of a 2048 variant on boards with walls
+    pub fn with_rules(profile: CircuitProfile, rules: impl RuleSet + 'static) -> Self {
+        let (builder, targets) = Game2048Circuit::build_obstacle_circuit_with_rules(profile, &rules);
+        let circuit = builder.build::<C>();
+        let verifier = circuit.verifier_data();
+        Self { circuit: circuit.prover_data(), verifier, targets, rules: Box::new(rules) }
+    }
+
+    /// Prove a move on a board with walls, after checking it with the rules' `check_move_with_obstacles`
+    pub fn prove(
+        &self,
+        before_board: &Board,
+        after_board: &Board,
+        obstacles: &ObstacleMask,
+        direction: Direction,
+    ) -> Result<ObstacleMoveProof, Game2048Error> {
+        self.rules.check_move_with_obstacles(before_board, after_board, obstacles, direction)?;
+
+        let mut pw = PartialWitness::<F>::new();
+        self.targets
+            .set_witness(&mut pw, before_board, after_board, obstacles, direction)?;
+        let proof = self.circuit.prove(pw).map_err(Game2048Error::ProofFailure)?;
+        Ok(ObstacleMoveProof { proof })
+    }
+
+    /// Verifier data of the obstacle circuit, e.g. to build a `Game2048Compressor`
+    pub fn verifier_data(&self) -> &VerifierCircuitData<F, C, D> {
+        &self.verifier
+    }
+
+    pub fn verify(&self, proof: &ObstacleMoveProof) -> Result<(), Game2048Error> {
+        self.verifier
+            .verify(proof.proof.clone())
+            .map_err(Game2048Error::ProofFailure)
+    }
+}
@@ -0,0 +1,68 @@
+use plonky2::fri::reduction_strategies::FriReductionStrategy;
+use plonky2::fri::FriConfig;
+use plonky2::plonk::circuit_data::CircuitConfig;
+
+/// Named circuit configurations, trading prover time against proof size
+///
+/// Every profile targets the same 100 bits of security as
+/// `standard_recursion_config`: rate_bits * num_query_rounds + proof_of_work_bits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CircuitProfile {
+    /// Little grinding and many queries: quickest to prove, largest proofs
+    FastProve,
+    /// High FRI rate, few queries and more grinding: slowest to prove, smallest proofs
+    SmallProof,
+    /// `standard_recursion_config`, which recursive verifier circuits expect
+    #[default]
+    Recursion,
+    /// `Recursion` with zero knowledge enabled, so proofs don't leak witness values.
+    /// Blinding pads the move circuit from 2^6 to 2^14 rows.
+    ZeroKnowledge,
+}
+
+impl CircuitProfile {
+    pub const ALL: [CircuitProfile; 4] = [
+        CircuitProfile::FastProve,
+        CircuitProfile::SmallProof,
+        CircuitProfile::Recursion,
+        CircuitProfile::ZeroKnowledge,
+    ];
+
+    /// Profile name, as used by the benchmarks
+    pub fn name(self) -> &'static str {
+        match self {
+            CircuitProfile::FastProve => "fast-prove",
+            CircuitProfile::SmallProof => "small-proof",
+            CircuitProfile::Recursion => "recursion",
+            CircuitProfile::ZeroKnowledge => "zero-knowledge",
+        }
+    }
+
+    pub fn config(self) -> CircuitConfig {
+        let standard = CircuitConfig::standard_recursion_config();
+        match self {
+            // The rate can't go below 2^-3: the Poseidon gate hashing the public inputs
+            // has degree 7, so the quotient degree factor must stay at 8
+            CircuitProfile::FastProve => CircuitConfig {
+                fri_config: FriConfig {
+                    proof_of_work_bits: 4,
+                    num_query_rounds: 32,
+                    ..standard.fri_config
+                },
+                ..standard
+            },
+            CircuitProfile::SmallProof => CircuitConfig {
+                fri_config: FriConfig {
+                    rate_bits: 7,
+                    cap_height: 2,
+                    proof_of_work_bits: 20,
+                    reduction_strategy: FriReductionStrategy::ConstantArityBits(4, 5),
+                    num_query_rounds: 12,
+                },
+                ..standard
+            },
+            CircuitProfile::Recursion => standard,
+            CircuitProfile::ZeroKnowledge => CircuitConfig::standard_recursion_zk_config(),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::anyhow;
+use plonky2::field::types::PrimeField64;
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_data::{ProverCircuitData, VerifierCircuitData};
+use plonky2::plonk::config::{GenericConfig, GenericHashOut, Hasher, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::serialization::DefaultGateSerializer;
+
+use super::{
+    Board, CircuitProfile, ClassicRules, Direction, Game2048Circuit, Game2048Error, Game2048Targets, IdentityTargets,
+    PlayerIdentity, RuleSet, D, F, IDENTITY_LEN,
+};
+
+/// Hash output of the config's hasher, e.g. a circuit digest
+pub type HashOutput<C> = <<C as GenericConfig<D>>::Hasher as Hasher<F>>::Hash;
+
+/// A proof of a single move, with the boards, the direction and the player identity as public inputs
+#[derive(Clone, Debug)]
+pub struct Game2048Proof<C: GenericConfig<D, F = F> = PoseidonGoldilocksConfig> {
+    pub proof: ProofWithPublicInputs<F, C, D>,
+}
+
+impl<C: GenericConfig<D, F = F>> Game2048Proof<C> {
+    fn public_tiles(&self, offset: usize) -> Board {
+        let mut board = [0; 16];
+        for (tile, input) in board.iter_mut().zip(&self.proof.public_inputs[offset..offset + 16]) {
+            *tile = input.to_canonical_u64();
+        }
+        board
+    }
+
+    pub fn before_board(&self) -> Board {
+        self.public_tiles(0)
+    }
+
+    pub fn after_board(&self) -> Board {
+        self.public_tiles(16)
+    }
+
+    pub fn direction(&self) -> Option<Direction> {
+        Direction::from_u64(self.proof.public_inputs[32].to_canonical_u64())
+    }
+
+    pub fn player_identity(&self) -> PlayerIdentity {
+        PlayerIdentity::from_elements(&self.proof.public_inputs[33..33 + IDENTITY_LEN])
+    }
+
+    /// Check that the proof was made for this player and session
+    pub fn check_player_identity(&self, expected: &PlayerIdentity) -> Result<(), Game2048Error> {
+        if self.player_identity() != *expected {
+            return Err(Game2048Error::IdentityMismatch);
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.proof.to_bytes()
+    }
+}
+
+/// Proves moves with a move circuit that is built once
+///
+/// Proofs use Poseidon by default. Another config is chosen with the type parameter,
+/// e.g. `Game2048Prover::<KeccakGoldilocksConfig>::with_config()` for consumers that
+/// hash with Keccak. `with_profile` also selects the circuit profile and `with_rules`
+/// the rules of a 2048 variant.
+///
+/// The move circuit gets the player identity as extra public inputs after the
+/// direction. The proof commits to its public inputs, so it can't be claimed for
+/// another player or session.
+pub struct Game2048Prover<C: GenericConfig<D, F = F> = PoseidonGoldilocksConfig> {
+    circuit: ProverCircuitData<F, C, D>,
+    verifier: VerifierCircuitData<F, C, D>,
+    targets: Game2048Targets,
+    identity: IdentityTargets,
+    rules: Box<dyn RuleSet>,
+}
+
+impl Game2048Prover {
+    pub fn new() -> Self {
+        Self::with_config()
+    }
+}
+
+impl<C: GenericConfig<D, F = F>> Game2048Prover<C> {
+    pub fn with_config() -> Self {
+        Self::with_profile(CircuitProfile::default())
+    }
+
+    pub fn with_profile(profile: CircuitProfile) -> Self {
+        Self::with_rules(profile, ClassicRules)
+    }
+
+    pub fn with_rules(profile: CircuitProfile, rules: impl RuleSet + 'static) -> Self {
+        let (mut builder, targets) = Game2048Circuit::build_circuit_with_rules(profile, &rules);
+        let identity = Game2048Circuit::add_identity(&mut builder);
+        builder.register_public_inputs(&identity.targets());
+
+        let circuit = builder.build::<C>();
+        let verifier = circuit.verifier_data();
+        Self { circuit: circuit.prover_data(), verifier, targets, identity, rules: Box::new(rules) }
+    }
+
+    /// Rules of the moves this prover proves
+    pub fn rules(&self) -> &dyn RuleSet {
+        self.rules.as_ref()
+    }
+
+    /// Prove that after_board follows from before_board by a move in the given
+    /// direction, for this player and session
+    ///
+    /// The move is checked natively first, so invalid tiles and illegal moves are
+    /// reported as such instead of as a failure inside plonky2.
+    pub fn prove(
+        &self,
+        before_board: &Board,
+        after_board: &Board,
+        direction: Direction,
+        identity: &PlayerIdentity,
+    ) -> Result<Game2048Proof<C>, Game2048Error> {
+        self.rules.check_move(before_board, after_board, direction)?;
+
+        let mut pw = PartialWitness::<F>::new();
+        self.targets.set_witness(&mut pw, before_board, after_board, direction)?;
+        self.identity.set_witness(&mut pw, identity)?;
+        let proof = self.circuit.prove(pw).map_err(Game2048Error::ProofFailure)?;
+        Ok(Game2048Proof { proof })
+    }
+
+    /// Verifier for the proofs of this prover
+    pub fn verifier(&self) -> Game2048Verifier<C> {
+        Game2048Verifier { circuit: self.verifier.clone() }
+    }
+}
+
+impl Default for Game2048Prover {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Verifies move proofs
+pub struct Game2048Verifier<C: GenericConfig<D, F = F> = PoseidonGoldilocksConfig> {
+    circuit: VerifierCircuitData<F, C, D>,
+}
+
+impl<C: GenericConfig<D, F = F>> Game2048Verifier<C> {
+    pub fn verify(&self, proof: &Game2048Proof<C>) -> Result<(), Game2048Error> {
+        self.circuit
+            .verify(proof.proof.clone())
+            .map_err(Game2048Error::ProofFailure)
+    }
+
+    /// Verifier data of the move circuit, e.g. to build a `Game2048Compressor`
+    pub fn verifier_data(&self) -> &VerifierCircuitData<F, C, D> {
+        &self.circuit
+    }
+
+    /// Digest identifying the circuit these proofs are for
+    pub fn circuit_digest(&self) -> HashOutput<C> {
+        self.circuit.verifier_only.circuit_digest
+    }
+
+    /// Check that this verifier is for the circuit with the expected digest, e.g. one
+    /// published alongside stored verifier data
+    pub fn check_circuit_digest(&self, expected: &HashOutput<C>) -> Result<(), Game2048Error> {
+        let actual = self.circuit_digest();
+        if actual != *expected {
+            return Err(Game2048Error::CircuitDigestMismatch {
+                expected: expected.to_vec(),
+                actual: actual.to_vec(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Game2048Error> {
+        self.circuit
+            .to_bytes(&DefaultGateSerializer)
+            .map_err(|err| Game2048Error::SerializationFailure(anyhow!("verifier data: {}", err)))
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Game2048Error> {
+        let circuit = VerifierCircuitData::from_bytes(bytes, &DefaultGateSerializer)
+            .map_err(|err| Game2048Error::DeserializationFailure(anyhow!("verifier data: {}", err)))?;
+        Ok(Self { circuit })
+    }
+
+    /// Read back a proof produced by `Game2048Proof::to_bytes`
+    pub fn proof_from_bytes(&self, bytes: Vec<u8>) -> Result<Game2048Proof<C>, Game2048Error> {
+        let proof = ProofWithPublicInputs::from_bytes(bytes, &self.circuit.common)
+            .map_err(|err| Game2048Error::DeserializationFailure(anyhow!("proof: {}", err)))?;
+        Ok(Game2048Proof { proof })
+    }
+}
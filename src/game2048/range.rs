@@ -0,0 +1,71 @@
+//! Range checks that fail to prove instead of panicking.
+//!
+//! Used where the prover controls the value being checked, e.g. timestamp intervals
+//! and the tiles of variant rules, so that a forged witness returns an error.
+
+use anyhow::Result;
+use plonky2::field::extension::Extendable;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+
+use super::{Game2048Circuit, D, F};
+
+impl Game2048Circuit {
+    /// Constrain x to num_bits bits
+    ///
+    /// `CircuitBuilder::range_check` panics while generating the witness of an out of
+    /// range value. Here only the low bits are generated, so an out of range value
+    /// conflicts with its recombination and fails to prove instead.
+    pub(crate) fn range_check_bits(builder: &mut CircuitBuilder<F, D>, x: Target, num_bits: usize) {
+        let bits: Vec<_> = (0..num_bits).map(|_| builder.add_virtual_bool_target_safe()).collect();
+        builder.add_simple_generator(LowBitsGenerator { x, bits: bits.iter().map(|bit| bit.target).collect() });
+
+        let mut recombined = builder.zero();
+        for bit in bits.iter().rev() {
+            recombined = builder.mul_const_add(F::TWO, recombined, bit.target);
+        }
+        builder.connect(recombined, x);
+    }
+}
+
+/// Writes the low bits of a value, little endian
+#[derive(Debug, Default)]
+struct LowBitsGenerator {
+    x: Target,
+    bits: Vec<Target>,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for LowBitsGenerator {
+    fn id(&self) -> String {
+        "LowBitsGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.x]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> Result<()> {
+        let x = witness.get_target(self.x).to_canonical_u64();
+        for (i, &bit) in self.bits.iter().enumerate() {
+            out_buffer.set_target(bit, F::from_bool((x >> i) & 1 == 1))?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target(self.x)?;
+        dst.write_target_vec(&self.bits)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let x = src.read_target()?;
+        let bits = src.read_target_vec()?;
+        Ok(Self { x, bits })
+    }
+}
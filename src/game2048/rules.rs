@@ -0,0 +1,396 @@
+//! Rule sets: the tile, merge and spawn rules of a 2048 variant, natively and in circuit.
+//!
+//! Board geometry, i.e. which cells form a line in each direction, is shared by every
+//! variant. A `RuleSet` decides which tiles exist, which neighbours merge and into
+//! what, what a merge scores and which value a spawned tile takes. `ClassicRules`
+//! are the original 2048 rules, used by `Game2048Engine` and every circuit unless
+//! another rule set is given.
+
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::HashOut;
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+
+use super::lookup::tile_from_exponent;
+use super::{
+    Board, CellMismatch, Cube, CubeCellMismatch, CubeDirection, Direction, Game2048Circuit, Game2048Engine,
+    Game2048Error, ObstacleMask, D, F, OPENING_TILES,
+};
+
+/// A line merged in circuit, with the points it scored
+#[derive(Clone, Copy, Debug)]
+pub struct MergedRow {
+    pub tiles: [Target; 4],
+    pub score: Target,
+}
+
+/// The rules of a 2048 variant
+///
+/// Implementors provide the merge predicate, merged value and spawn distribution both
+/// natively and as gadgets, which must agree. The provided methods build the native
+/// engine on top of them; variants that don't compact lines before merging override
+/// `merge_row_scored` and `add_merge_row`.
+pub trait RuleSet: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether a value can appear on the board, 0 being an empty cell
+    fn is_valid_tile(&self, value: u64) -> bool;
+
+    /// Whether two nonzero neighbouring tiles merge, the first one being closer to
+    /// the edge the line moves toward
+    fn can_merge(&self, a: u64, b: u64) -> bool;
+
+    /// The tile two merging tiles become
+    fn merged_value(&self, a: u64, b: u64) -> u64;
+
+    /// Points scored by a merge
+    fn merge_score(&self, a: u64, b: u64) -> u64 {
+        self.merged_value(a, b)
+    }
+
+    /// Value of a spawned tile, from 32 random bits
+    fn spawn_value(&self, random: u64) -> u64;
+
+    /// In-circuit `can_merge`, which must be false when either tile is 0
+    fn add_can_merge(&self, builder: &mut CircuitBuilder<F, D>, a: Target, b: Target) -> BoolTarget;
+
+    /// In-circuit `merged_value`, only used where `add_can_merge` holds
+    fn add_merged_value(&self, builder: &mut CircuitBuilder<F, D>, a: Target, b: Target) -> Target;
+
+    /// In-circuit `merge_score`, given the merged value
+    fn add_merge_score(&self, _builder: &mut CircuitBuilder<F, D>, _a: Target, _b: Target, merged: Target) -> Target {
+        merged
+    }
+
+    /// Constrain a tile of either board of a move to the values `is_valid_tile` accepts,
+    /// for rules whose merge gadgets are only sound on such tiles
+    fn add_tile_check(&self, _builder: &mut CircuitBuilder<F, D>, _tile: Target) {}
+
+    /// In-circuit `spawn_value`, for a random value below 2^32
+    fn add_spawn_value(&self, builder: &mut CircuitBuilder<F, D>, random: Target) -> Target;
+
+    /// Merge a line toward index 0, returning the merged line and its score
+    ///
+    /// Nonzero tiles are compacted, then neighbours merge at most once each, from index 0.
+    fn merge_row_scored(&self, row: [u64; 4]) -> ([u64; 4], u64) {
+        let tiles: Vec<u64> = row.iter().copied().filter(|&tile| tile != 0).collect();
+
+        let mut merged = [0; 4];
+        let mut score = 0;
+        let mut out = 0;
+        let mut i = 0;
+        while i < tiles.len() {
+            if i + 1 < tiles.len() && self.can_merge(tiles[i], tiles[i + 1]) {
+                merged[out] = self.merged_value(tiles[i], tiles[i + 1]);
+                score += self.merge_score(tiles[i], tiles[i + 1]);
+                i += 2;
+            } else {
+                merged[out] = tiles[i];
+                i += 1;
+            }
+            out += 1;
+        }
+        (merged, score)
+    }
+
+    /// In-circuit `merge_row_scored`
+    fn add_merge_row(&self, builder: &mut CircuitBuilder<F, D>, row: [Target; 4]) -> MergedRow {
+        Game2048Circuit::add_compact_merge(builder, self, row)
+    }
+
+    /// Merge a single line toward index 0
+    fn merge_row(&self, row: [u64; 4]) -> [u64; 4] {
+        self.merge_row_scored(row).0
+    }
+
+    /// Check every tile of a board
+    fn validate_board(&self, board: &Board) -> Result<(), Game2048Error> {
+        match board.iter().position(|&value| !self.is_valid_tile(value)) {
+            Some(cell) => Err(Game2048Error::InvalidTile { cell, value: board[cell] }),
+            None => Ok(()),
+        }
+    }
+
+    /// Apply a move to a row-major 4x4 board, returning the board and the points scored
+    fn apply_move_scored(&self, board: &Board, direction: Direction) -> (Board, u64) {
+        let mut after = [0; 16];
+        let mut score = 0;
+        for line in Game2048Engine::lines(direction) {
+            let (merged, line_score) = self.merge_row_scored(line.map(|cell| board[cell]));
+            for (&cell, tile) in line.iter().zip(merged) {
+                after[cell] = tile;
+            }
+            score += line_score;
+        }
+        (after, score)
+    }
+
+    /// Apply a move to a row-major 4x4 board
+    fn apply_move(&self, board: &Board, direction: Direction) -> Board {
+        self.apply_move_scored(board, direction).0
+    }
+
+    /// Cells of after_board that differ from applying the move to before_board
+    fn diagnose_move(&self, before_board: &Board, after_board: &Board, direction: Direction) -> Vec<CellMismatch> {
+        Game2048Engine::mismatches(&self.apply_move(before_board, direction), after_board, direction)
+    }
+
+    /// Check a move before proving it, reporting invalid tiles or every cell that disagrees
+    fn check_move(&self, before_board: &Board, after_board: &Board, direction: Direction) -> Result<(), Game2048Error> {
+        self.validate_board(before_board)?;
+        self.validate_board(after_board)?;
+        let mismatches = self.diagnose_move(before_board, after_board, direction);
+        if !mismatches.is_empty() {
+            return Err(Game2048Error::IllegalMove { direction, mismatches });
+        }
+        Ok(())
+    }
+
+    /// Cells a spawn may take after a move in direction, or on the opening board when
+    /// direction is None
+    ///
+    /// Every cell by default.
+    fn spawn_cells(&self, _direction: Option<Direction>) -> [bool; 16] {
+        [true; 16]
+    }
+
+    /// In-circuit `spawn_cells`, for a direction that is one of 0..4
+    ///
+    /// Each cell selects its flag from the four directions' masks, or is a constant when
+    /// the masks agree, as they do by default.
+    fn add_spawn_cells(&self, builder: &mut CircuitBuilder<F, D>, direction: Option<Target>) -> [BoolTarget; 16] {
+        let Some(direction) = direction else {
+            return self.spawn_cells(None).map(|allowed| builder.constant_bool(allowed));
+        };
+        let masks = Direction::ALL.map(|direction| self.spawn_cells(Some(direction)));
+        if masks.iter().all(|mask| *mask == masks[0]) {
+            return masks[0].map(|allowed| builder.constant_bool(allowed));
+        }
+
+        let direction_index = Game2048Circuit::direction_index(builder, direction);
+        std::array::from_fn(|cell| {
+            let flags = masks.iter().map(|mask| builder.constant_bool(mask[cell]).target).collect();
+            BoolTarget::new_unsafe(builder.random_access(direction_index, flags))
+        })
+    }
+
+    /// Cell and value of a spawn after a move in direction, or None when no cell the
+    /// spawn may take is empty
+    fn spawn_position(
+        &self,
+        board: &Board,
+        seed: &HashOut<F>,
+        spawn_index: u64,
+        direction: Option<Direction>,
+    ) -> Option<(usize, u64)> {
+        let allowed = self.spawn_cells(direction);
+        let empty_cells: Vec<usize> = (0..16).filter(|&cell| allowed[cell] && board[cell] == 0).collect();
+        if empty_cells.is_empty() {
+            return None;
+        }
+
+        let (low, high) = Game2048Engine::spawn_randomness(seed, spawn_index);
+        let cell = empty_cells[(low % empty_cells.len() as u64) as usize];
+        Some((cell, self.spawn_value(high)))
+    }
+
+    /// The board with spawn number spawn_index added after a move in direction
+    fn spawn_tile(&self, board: &Board, seed: &HashOut<F>, spawn_index: u64, direction: Option<Direction>) -> Board {
+        let mut spawned = *board;
+        if let Some((cell, value)) = self.spawn_position(board, seed, spawn_index, direction) {
+            spawned[cell] = value;
+        }
+        spawned
+    }
+
+    /// The opening board of a game with this seed
+    fn opening_board(&self, seed: &HashOut<F>) -> Board {
+        (0..OPENING_TILES).fold([0; 16], |board, spawn_index| self.spawn_tile(&board, seed, spawn_index, None))
+    }
+
+    /// Every board of a game: the opening board, then the board after each move and its spawn
+    fn play(&self, seed: &HashOut<F>, directions: &[Direction]) -> Vec<Board> {
+        self.play_from(seed, &self.opening_board(seed), 0, directions)
+    }
+
+    /// The boards reached from initial_board, the board at move start_move, by each
+    /// move and its spawn, starting with initial_board
+    fn play_from(
+        &self,
+        seed: &HashOut<F>,
+        initial_board: &Board,
+        start_move: u64,
+        directions: &[Direction],
+    ) -> Vec<Board> {
+        let mut boards = vec![*initial_board];
+        for (n, &direction) in directions.iter().enumerate() {
+            let moved_board = self.apply_move(boards.last().unwrap(), direction);
+            let spawn_index = OPENING_TILES + start_move + n as u64;
+            boards.push(self.spawn_tile(&moved_board, seed, spawn_index, Some(direction)));
+        }
+        boards
+    }
+
+    /// Merge a single line toward index 0, separately within each run between walls
+    fn merge_row_with_walls(&self, row: [u64; 4], walls: [bool; 4]) -> [u64; 4] {
+        let mut merged = [0; 4];
+        let mut start = 0;
+        while start < 4 {
+            if walls[start] {
+                start += 1;
+                continue;
+            }
+            let end = (start..4).find(|&cell| walls[cell]).unwrap_or(4);
+            let mut run = [0; 4];
+            run[..end - start].copy_from_slice(&row[start..end]);
+            merged[start..end].copy_from_slice(&self.merge_row(run)[..end - start]);
+            start = end;
+        }
+        merged
+    }
+
+    /// Apply a move to a board with walls
+    fn apply_move_with_obstacles(&self, board: &Board, obstacles: &ObstacleMask, direction: Direction) -> Board {
+        let mut after = [0; 16];
+        for line in Game2048Engine::lines(direction) {
+            let merged = self.merge_row_with_walls(line.map(|cell| board[cell]), line.map(|cell| obstacles[cell]));
+            for (&cell, tile) in line.iter().zip(merged) {
+                after[cell] = tile;
+            }
+        }
+        after
+    }
+
+    /// `check_move` on a board with walls, which must not hold tiles
+    fn check_move_with_obstacles(
+        &self,
+        before_board: &Board,
+        after_board: &Board,
+        obstacles: &ObstacleMask,
+        direction: Direction,
+    ) -> Result<(), Game2048Error> {
+        self.validate_board(before_board)?;
+        self.validate_board(after_board)?;
+        if let Some(cell) = (0..16).find(|&cell| obstacles[cell] && before_board[cell] != 0) {
+            return Err(Game2048Error::TileOnObstacle { cell });
+        }
+
+        let expected_board = self.apply_move_with_obstacles(before_board, obstacles, direction);
+        let mismatches = Game2048Engine::mismatches(&expected_board, after_board, direction);
+        if !mismatches.is_empty() {
+            return Err(Game2048Error::IllegalMove { direction, mismatches });
+        }
+        Ok(())
+    }
+
+    /// Check every tile of a cube
+    fn validate_cube(&self, cube: &Cube) -> Result<(), Game2048Error> {
+        match cube.iter().position(|&value| !self.is_valid_tile(value)) {
+            Some(cell) => Err(Game2048Error::InvalidTile { cell, value: cube[cell] }),
+            None => Ok(()),
+        }
+    }
+
+    /// Apply a move to a cube
+    fn apply_cube_move(&self, cube: &Cube, direction: CubeDirection) -> Cube {
+        let mut after = [0; 64];
+        for line in Game2048Engine::cube_lines(direction) {
+            let merged = self.merge_row(line.map(|cell| cube[cell]));
+            for (&cell, tile) in line.iter().zip(merged) {
+                after[cell] = tile;
+            }
+        }
+        after
+    }
+
+    /// Check a cube move before proving it, reporting invalid tiles or every cell that disagrees
+    fn check_cube_move(
+        &self,
+        before_cube: &Cube,
+        after_cube: &Cube,
+        direction: CubeDirection,
+    ) -> Result<(), Game2048Error> {
+        self.validate_cube(before_cube)?;
+        self.validate_cube(after_cube)?;
+        let expected_cube = self.apply_cube_move(before_cube, direction);
+        let mismatches: Vec<_> = (0..64)
+            .filter(|&cell| expected_cube[cell] != after_cube[cell])
+            .map(|cell| CubeCellMismatch {
+                x: cell % 4,
+                y: cell / 4 % 4,
+                z: cell / 16,
+                expected: expected_cube[cell],
+                actual: after_cube[cell],
+            })
+            .collect();
+        if !mismatches.is_empty() {
+            return Err(Game2048Error::IllegalCubeMove { direction, mismatches });
+        }
+        Ok(())
+    }
+}
+
+/// One spawn in this many is a 4 under the classic rules
+const FOUR_ODDS: u64 = 10;
+
+/// The largest classic tile is 2^MAX_TILE_EXPONENT, so that the tiles the circuit
+/// selects from, 0 and 2^1..=2^MAX_TILE_EXPONENT, fill a 5-bit index
+pub const MAX_TILE_EXPONENT: u32 = 31;
+
+/// The original rules: powers of two, equal tiles merge into their sum, which is
+/// also the score, and one spawn in ten is a 4
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClassicRules;
+
+impl RuleSet for ClassicRules {
+    fn name(&self) -> &'static str {
+        "classic"
+    }
+
+    fn is_valid_tile(&self, value: u64) -> bool {
+        value == 0 || (value >= 2 && value.is_power_of_two() && value <= 1 << MAX_TILE_EXPONENT)
+    }
+
+    fn can_merge(&self, a: u64, b: u64) -> bool {
+        a == b
+    }
+
+    fn merged_value(&self, a: u64, _b: u64) -> u64 {
+        a * 2
+    }
+
+    fn spawn_value(&self, random: u64) -> u64 {
+        if random.is_multiple_of(FOUR_ODDS) { 4 } else { 2 }
+    }
+
+    fn add_can_merge(&self, builder: &mut CircuitBuilder<F, D>, a: Target, b: Target) -> BoolTarget {
+        let zero = builder.zero();
+        let equal = builder.is_equal(a, b);
+        let a_is_zero = builder.is_equal(a, zero);
+        let a_nonzero = builder.not(a_is_zero);
+        builder.and(equal, a_nonzero)
+    }
+
+    fn add_merged_value(&self, builder: &mut CircuitBuilder<F, D>, a: Target, _b: Target) -> Target {
+        builder.mul_const(F::TWO, a)
+    }
+
+    /// The tile is the entry at its exponent, given as a hint, among every valid tile
+    fn add_tile_check(&self, builder: &mut CircuitBuilder<F, D>, tile: Target) {
+        let exponent = Game2048Circuit::add_exponent_hint(builder, tile, MAX_TILE_EXPONENT);
+        let tiles = (0..=MAX_TILE_EXPONENT)
+            .map(|exponent| builder.constant(F::from_canonical_u64(tile_from_exponent(exponent))))
+            .collect();
+        let selected = builder.random_access(exponent, tiles);
+        builder.connect(selected, tile);
+    }
+
+    fn add_spawn_value(&self, builder: &mut CircuitBuilder<F, D>, random: Target) -> Target {
+        let zero = builder.zero();
+        let ten = builder.constant(F::from_canonical_u64(FOUR_ODDS));
+        let (_, odds) = Game2048Circuit::div_rem(builder, random, ten);
+        let is_four = builder.is_equal(odds, zero);
+        let two = builder.two();
+        builder.mul_const_add(F::TWO, is_four.target, two)
+    }
+}
@@ -0,0 +1,105 @@
+//! Commit-reveal protocol for the seed that drives tile spawns.
+//!
+//! 1. The server picks a secret `ServerSeed` and publishes its commitment, Poseidon(secret).
+//! 2. The player, having seen the commitment, contributes `PlayerEntropy`.
+//! 3. The seed is Poseidon(secret || entropy || identity): the server can't pick it
+//!    without knowing the entropy in advance, and the player can't pick it without
+//!    the secret. The `PlayerIdentity` ties the seed to one player and session.
+//!
+//! Circuits reveal the secret only as a witness; their proofs expose the server
+//! commitment, the player entropy and the identity, which a verifier compares with
+//! the published commitment, the entropy it saw the player send and the player it
+//! expects.
+
+use plonky2::field::types::Sample;
+use plonky2::hash::hash_types::{HashOut, HashOutTarget};
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::config::Hasher;
+
+use super::{Game2048Circuit, Game2048Engine, Game2048Error, IdentityTargets, PlayerIdentity, D, F};
+
+/// Entropy contributed by the player once the server has committed to its seed
+pub type PlayerEntropy = [F; 4];
+
+/// The server's secret half of the seed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ServerSeed(pub [F; 4]);
+
+impl ServerSeed {
+    pub fn random() -> Self {
+        Self(F::rand_array())
+    }
+
+    /// Commitment published before the player contributes entropy
+    pub fn commitment(&self) -> HashOut<F> {
+        PoseidonHash::hash_no_pad(&self.0)
+    }
+}
+
+impl Game2048Engine {
+    /// The seed of a game: Poseidon(server_seed || player_entropy || identity)
+    pub fn combine_seed(
+        server_seed: &ServerSeed,
+        player_entropy: &PlayerEntropy,
+        identity: &PlayerIdentity,
+    ) -> HashOut<F> {
+        PoseidonHash::hash_no_pad(&[server_seed.0.as_slice(), player_entropy, &identity.elements()].concat())
+    }
+
+    /// Check a revealed server seed against the commitment published for it
+    pub fn check_seed_reveal(commitment: &HashOut<F>, server_seed: &ServerSeed) -> Result<(), Game2048Error> {
+        if server_seed.commitment() != *commitment {
+            return Err(Game2048Error::SeedCommitmentMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Targets of the seed derivation
+#[derive(Clone, Copy, Debug)]
+pub struct SeedTargets {
+    pub server_seed: [Target; 4],
+    pub player_entropy: [Target; 4],
+    pub identity: IdentityTargets,
+    /// Poseidon(server_seed), to be made public
+    pub server_commitment: HashOutTarget,
+    /// Poseidon(server_seed || player_entropy || identity), for the spawn derivation
+    pub seed: HashOutTarget,
+}
+
+impl SeedTargets {
+    pub fn set_witness(
+        &self,
+        pw: &mut PartialWitness<F>,
+        server_seed: &ServerSeed,
+        player_entropy: &PlayerEntropy,
+        identity: &PlayerIdentity,
+    ) -> Result<(), Game2048Error> {
+        pw.set_target_arr(&self.server_seed, &server_seed.0)
+            .map_err(Game2048Error::WitnessConflict)?;
+        pw.set_target_arr(&self.player_entropy, player_entropy)
+            .map_err(Game2048Error::WitnessConflict)?;
+        self.identity.set_witness(pw, identity)
+    }
+}
+
+impl Game2048Circuit {
+    /// Add the seed derivation, without registering any public inputs
+    ///
+    /// Circuits using the seed should register `server_commitment`, `player_entropy`
+    /// and `identity.targets()`.
+    pub fn add_seed(builder: &mut CircuitBuilder<F, D>) -> SeedTargets {
+        let server_seed = builder.add_virtual_target_arr::<4>();
+        let player_entropy = builder.add_virtual_target_arr::<4>();
+        let identity = Self::add_identity(builder);
+
+        let server_commitment = builder.hash_n_to_hash_no_pad::<PoseidonHash>(server_seed.to_vec());
+        let inputs = [server_seed.as_slice(), &player_entropy, &identity.targets()].concat();
+        let seed = builder.hash_n_to_hash_no_pad::<PoseidonHash>(inputs);
+
+        SeedTargets { server_seed, player_entropy, identity, server_commitment, seed }
+    }
+}
@@ -0,0 +1,251 @@
+//! Deterministic tile spawns, shared by the native engine and the circuits.
+//!
+//! Spawn number `spawn_index` of a game with seed `seed` is derived as follows:
+//!
+//! 1. h = Poseidon(seed || spawn_index), and h[0] is split canonically into 32-bit
+//!    halves `low` and `high`.
+//! 2. The tile goes in empty cell number `low mod empty_count`, counting in row-major
+//!    order the empty cells that `RuleSet::spawn_cells` allows after the move, which
+//!    is every cell under the classic rules.
+//! 3. Its value is `RuleSet::spawn_value(high)`. Under the classic rules it is a 4 when
+//!    `high mod 10 == 0` and a 2 otherwise, so about one spawn in ten is a 4.
+//!
+//! A board with no such empty cell gets no tile.
+
+use anyhow::Result;
+use plonky2::field::extension::Extendable;
+use plonky2::field::types::{Field, PrimeField64};
+use plonky2::hash::hash_types::{HashOut, HashOutTarget, RichField};
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, PartitionWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::plonk::config::Hasher;
+use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+
+use super::{
+    Board, CircuitProfile, ClassicRules, Direction, Game2048Circuit, Game2048Engine, Game2048Error, Game2048Targets,
+    PlayerEntropy, PlayerIdentity, RuleSet, SeedTargets, ServerSeed, D, F,
+};
+
+impl Game2048Engine {
+    /// Cell and value of a spawn, or None when the board is full
+    pub fn spawn_position(board: &Board, seed: &HashOut<F>, spawn_index: u64) -> Option<(usize, u64)> {
+        ClassicRules.spawn_position(board, seed, spawn_index, None)
+    }
+
+    /// The board with spawn number spawn_index added
+    pub fn spawn_tile(board: &Board, seed: &HashOut<F>, spawn_index: u64) -> Board {
+        ClassicRules.spawn_tile(board, seed, spawn_index, None)
+    }
+
+    /// The low and high 32 bits of the spawn hash
+    pub(crate) fn spawn_randomness(seed: &HashOut<F>, spawn_index: u64) -> (u64, u64) {
+        let inputs = [seed.elements.as_slice(), &[F::from_canonical_u64(spawn_index)]].concat();
+        let random = PoseidonHash::hash_no_pad(&inputs).elements[0].to_canonical_u64();
+        (random & 0xffff_ffff, random >> 32)
+    }
+}
+
+impl Game2048Circuit {
+    /// Add the spawn rule to a board, returning the board with the new tile
+    pub fn add_spawn(
+        builder: &mut CircuitBuilder<F, D>,
+        board: &[Target; 16],
+        seed: HashOutTarget,
+        spawn_index: Target,
+    ) -> [Target; 16] {
+        Self::add_spawn_with_rules(builder, &ClassicRules, board, seed, spawn_index, None)
+    }
+
+    /// `add_spawn` under the spawn rules of a 2048 variant, after a move in direction,
+    /// or on the opening board when direction is None
+    pub fn add_spawn_with_rules(
+        builder: &mut CircuitBuilder<F, D>,
+        rules: &dyn RuleSet,
+        board: &[Target; 16],
+        seed: HashOutTarget,
+        spawn_index: Target,
+        direction: Option<Target>,
+    ) -> [Target; 16] {
+        let zero = builder.zero();
+
+        let inputs = [seed.elements.as_slice(), &[spawn_index]].concat();
+        let random = builder.hash_n_to_hash_no_pad::<PoseidonHash>(inputs).elements[0];
+        let (low, high) = Self::split_canonical(builder, random);
+
+        // Only the cells the rules allow count as empty, which costs nothing when they allow every cell
+        let allowed = rules.add_spawn_cells(builder, direction);
+        let empty: [_; 16] = std::array::from_fn(|cell| {
+            let is_empty = builder.is_equal(board[cell], zero);
+            builder.and(is_empty, allowed[cell])
+        });
+        let empty_count = builder.add_many(empty.map(|flag| flag.target));
+
+        // A board without such a cell divides by one instead, and gets no tile
+        let is_full = builder.is_equal(empty_count, zero);
+        let has_space = builder.not(is_full);
+        let divisor = builder.add(empty_count, is_full.target);
+        let (_, chosen) = Self::div_rem(builder, low, divisor);
+
+        let value = rules.add_spawn_value(builder, high);
+        let value = builder.mul(value, has_space.target);
+
+        // The chosen cell is the empty cell with exactly `chosen` empty cells before it
+        let mut empty_before = zero;
+        let mut spawned = [zero; 16];
+        for (cell, (&tile, flag)) in board.iter().zip(empty).enumerate() {
+            let is_chosen = builder.is_equal(empty_before, chosen);
+            let is_spawn = builder.and(is_chosen, flag);
+            spawned[cell] = builder.mul_add(is_spawn.target, value, tile);
+            empty_before = builder.add(empty_before, flag.target);
+        }
+        spawned
+    }
+
+    /// Split a field element into its canonical low and high 32-bit halves
+    fn split_canonical(builder: &mut CircuitBuilder<F, D>, x: Target) -> (Target, Target) {
+        let (low, high) = builder.split_low_high(x, 32, 64);
+
+        // high = 2^32 - 1 with a nonzero low would be x + p, a second decomposition of x
+        let max_high = builder.constant(F::from_canonical_u64(u32::MAX as u64));
+        let high_is_max = builder.is_equal(high, max_high);
+        let overflow = builder.mul(high_is_max.target, low);
+        builder.assert_zero(overflow);
+
+        (low, high)
+    }
+
+    /// Quotient and remainder of a numerator below 2^32 by a divisor from 1 to 16
+    pub(crate) fn div_rem(builder: &mut CircuitBuilder<F, D>, numerator: Target, divisor: Target) -> (Target, Target) {
+        let quotient = builder.add_virtual_target();
+        let remainder = builder.add_virtual_target();
+        builder.add_simple_generator(DivRemGenerator { numerator, divisor, quotient, remainder });
+
+        // numerator = quotient * divisor + remainder cannot wrap around: it stays below 2^36
+        builder.range_check(quotient, 32);
+        builder.range_check(remainder, 4);
+        let recombined = builder.mul_add(quotient, divisor, remainder);
+        builder.connect(recombined, numerator);
+
+        // remainder < divisor
+        let one = builder.one();
+        let max_remainder = builder.sub(divisor, one);
+        let slack = builder.sub(max_remainder, remainder);
+        builder.range_check(slack, 4);
+
+        (quotient, remainder)
+    }
+}
+
+/// Computes the quotient and remainder checked by `div_rem`
+#[derive(Debug, Default)]
+struct DivRemGenerator {
+    numerator: Target,
+    divisor: Target,
+    quotient: Target,
+    remainder: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for DivRemGenerator {
+    fn id(&self) -> String {
+        "DivRemGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.numerator, self.divisor]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> Result<()> {
+        let numerator = witness.get_target(self.numerator).to_canonical_u64();
+        let divisor = witness.get_target(self.divisor).to_canonical_u64().max(1);
+        out_buffer.set_target(self.quotient, F::from_canonical_u64(numerator / divisor))?;
+        out_buffer.set_target(self.remainder, F::from_canonical_u64(numerator % divisor))
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target(self.numerator)?;
+        dst.write_target(self.divisor)?;
+        dst.write_target(self.quotient)?;
+        dst.write_target(self.remainder)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let numerator = src.read_target()?;
+        let divisor = src.read_target()?;
+        let quotient = src.read_target()?;
+        let remainder = src.read_target()?;
+        Ok(Self { numerator, divisor, quotient, remainder })
+    }
+}
+
+/// Targets of a move followed by a spawn
+#[derive(Clone, Copy, Debug)]
+pub struct SpawnMoveTargets {
+    /// before_board, after_board (including the spawned tile) and direction
+    pub game: Game2048Targets,
+    /// The board after the move, before the spawn
+    pub moved_board: [Target; 16],
+    pub seed: SeedTargets,
+    pub spawn_index: Target,
+}
+
+impl SpawnMoveTargets {
+    /// Assign a move and its spawn, returning the board after both
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_witness(
+        &self,
+        pw: &mut PartialWitness<F>,
+        before_board: &Board,
+        direction: Direction,
+        server_seed: &ServerSeed,
+        player_entropy: &PlayerEntropy,
+        identity: &PlayerIdentity,
+        spawn_index: u64,
+    ) -> Result<Board, Game2048Error> {
+        Game2048Engine::validate_board(before_board)?;
+        let moved_board = Game2048Engine::apply_move(before_board, direction);
+        let seed = Game2048Engine::combine_seed(server_seed, player_entropy, identity);
+        let after_board = Game2048Engine::spawn_tile(&moved_board, &seed, spawn_index);
+
+        self.game.set_witness(pw, before_board, &after_board, direction)?;
+        for (&target, &tile) in self.moved_board.iter().zip(&moved_board) {
+            pw.set_target(target, F::from_canonical_u64(tile))
+                .map_err(Game2048Error::WitnessConflict)?;
+        }
+        self.seed.set_witness(pw, server_seed, player_entropy, identity)?;
+        pw.set_target(self.spawn_index, F::from_canonical_u64(spawn_index))
+            .map_err(Game2048Error::WitnessConflict)?;
+        Ok(after_board)
+    }
+}
+
+impl Game2048Circuit {
+    /// Build the circuit for a move followed by a seeded spawn
+    ///
+    /// Public inputs are before_board (16), after_board (16) including the new tile,
+    /// direction (1), spawn_index (1), the server's seed commitment (4), the
+    /// player entropy (4) and the player identity (5).
+    pub fn build_spawn_circuit(profile: CircuitProfile) -> (CircuitBuilder<F, D>, SpawnMoveTargets) {
+        let mut builder = CircuitBuilder::<F, D>::new(profile.config());
+        let game = Self::add_move_targets(&mut builder);
+        let moved_board = builder.add_virtual_target_arr::<16>();
+        let spawn_index = builder.add_virtual_target();
+        let seed = Self::add_seed(&mut builder);
+
+        builder.register_public_input(spawn_index);
+        builder.register_public_inputs(&seed.server_commitment.elements);
+        builder.register_public_inputs(&seed.player_entropy);
+        builder.register_public_inputs(&seed.identity.targets());
+
+        Self::add_constraints(&mut builder, &game.before_board, &moved_board, game.direction);
+        let spawned = Self::add_spawn(&mut builder, &moved_board, seed.seed, spawn_index);
+        for (tile, after_tile) in spawned.into_iter().zip(game.after_board) {
+            builder.connect(tile, after_tile);
+        }
+
+        (builder, SpawnMoveTargets { game, moved_board, seed, spawn_index })
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use plonky2::field::types::PrimeField64;
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitData;
+use plonky2::plonk::config::GenericConfig;
+
+use super::{Direction, Game2048Circuit, Game2048Engine, Game2048Error, Game2048Targets, C, D, F};
+
+/// Size of a built move circuit and of its proofs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitStats {
+    /// Rows used by gates other than NoopGate padding
+    pub num_gates: usize,
+    /// Rows per gate type, keyed by gate id
+    pub gate_counts: BTreeMap<String, usize>,
+    pub degree_bits: usize,
+    pub num_public_inputs: usize,
+    /// Serialized size of a proof with its public inputs
+    pub proof_size: usize,
+}
+
+impl Game2048Circuit {
+    /// Build the move circuit, prove a sample move with it and report its size
+    pub fn circuit_stats() -> Result<CircuitStats, Game2048Error> {
+        CircuitStats::measure(Self::build_circuit())
+    }
+
+    /// Same as `circuit_stats`, for the lookup table circuit
+    pub fn lookup_circuit_stats() -> Result<CircuitStats, Game2048Error> {
+        CircuitStats::measure(Self::build_lookup_circuit())
+    }
+}
+
+impl CircuitStats {
+    /// Build a move circuit, prove a sample move with it and report its size
+    pub fn measure((builder, targets): (CircuitBuilder<F, D>, Game2048Targets)) -> Result<Self, Game2048Error> {
+        let circuit = builder.build::<C>();
+
+        let before_board = [
+            2, 2, 0, 4, //
+            0, 0, 4, 0, //
+            2, 0, 0, 2, //
+            0, 4, 0, 0, //
+        ];
+        let after_board = Game2048Engine::apply_move(&before_board, Direction::Left);
+
+        let mut pw = PartialWitness::<F>::new();
+        targets.set_witness(&mut pw, &before_board, &after_board, Direction::Left)?;
+        let proof = circuit.prove(pw).map_err(Game2048Error::ProofFailure)?;
+        circuit.verify(proof.clone()).map_err(Game2048Error::ProofFailure)?;
+
+        let gate_counts = Self::gate_counts(&circuit);
+        let num_gates = gate_counts
+            .iter()
+            .filter(|(id, _)| id.as_str() != "NoopGate")
+            .map(|(_, &count)| count)
+            .sum();
+
+        Ok(Self {
+            num_gates,
+            gate_counts,
+            degree_bits: circuit.common.degree_bits(),
+            num_public_inputs: circuit.common.num_public_inputs,
+            proof_size: proof.to_bytes().len(),
+        })
+    }
+
+    /// Count rows per gate type from the selector polynomials of a built circuit.
+    ///
+    /// Each row has its gate's index in exactly one selector polynomial, and an
+    /// "unused" marker in the others.
+    fn gate_counts<C: GenericConfig<D, F = F>>(circuit: &CircuitData<F, C, D>) -> BTreeMap<String, usize> {
+        let num_selectors = circuit.common.selectors_info.num_selectors();
+        let selectors: Vec<_> = circuit.prover_only.constants_sigmas_commitment.polynomials[..num_selectors]
+            .iter()
+            .map(|poly| poly.clone().fft().values)
+            .collect();
+
+        let mut counts = BTreeMap::new();
+        for row in 0..circuit.common.degree() {
+            let gate_index = selectors
+                .iter()
+                .map(|values| values[row].to_canonical_u64() as usize)
+                .find(|&index| index < circuit.common.gates.len());
+            if let Some(gate_index) = gate_index {
+                *counts.entry(circuit.common.gates[gate_index].0.id()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+}
+
+impl fmt::Display for CircuitStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "gates: {}", self.num_gates)?;
+        for (id, count) in &self.gate_counts {
+            writeln!(f, "  {count:>6}  {id}")?;
+        }
+        writeln!(f, "degree_bits: {}", self.degree_bits)?;
+        writeln!(f, "public inputs: {}", self.num_public_inputs)?;
+        write!(f, "proof size: {} bytes", self.proof_size)
+    }
+}
@@ -0,0 +1,204 @@
+//! Threes-style rules: 1 and 2 combine into 3, equal tiles from 3 up combine into
+//! their sum, and tiles move at most one step per swipe.
+//!
+//! Lines are not compacted. Scanning from the edge the line moves toward, the first
+//! cell that is empty or combines with the tile behind it takes that tile, and every
+//! tile further back follows it by one step. Everything in front of that cell stays.
+//!
+//! After a move, the new tile spawns in an empty cell of the edge the tiles moved away
+//! from, e.g. the right column after a move to the left. Opening tiles go anywhere.
+
+use anyhow::{anyhow, Result};
+use plonky2::field::extension::Extendable;
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::RichField;
+use plonky2::iop::generator::{GeneratedValues, SimpleGenerator};
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::iop::witness::{PartitionWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CommonCircuitData;
+use plonky2::util::serialization::{Buffer, IoResult, Read, Write};
+
+use super::{Direction, Game2048Circuit, Game2048Engine, MergedRow, RuleSet, D, F};
+
+/// Spawns are a 1, a 2 or a 3, equally likely
+const SPAWN_VALUES: u64 = 3;
+
+/// Number of valid tiles, so that the circuit selects a tile with a 5-bit index
+const NUM_TILES: usize = 32;
+
+/// The largest tile, the last of 0, 1, 2 and 3 * 2^0..=3 * 2^28
+pub const MAX_THREES_TILE: u64 = 3 << (NUM_TILES - 4);
+
+/// The valid tile at index, in increasing order
+fn tile_at(index: usize) -> u64 {
+    if index < 3 { index as u64 } else { 3 << (index - 3) }
+}
+
+/// Threes: tiles are 1, 2 and 3 times a power of two, and a combination scores the
+/// new tile
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ThreesRules;
+
+impl RuleSet for ThreesRules {
+    fn name(&self) -> &'static str {
+        "threes"
+    }
+
+    fn is_valid_tile(&self, value: u64) -> bool {
+        value <= 2 || (value <= MAX_THREES_TILE && value.is_multiple_of(3) && (value / 3).is_power_of_two())
+    }
+
+    fn can_merge(&self, a: u64, b: u64) -> bool {
+        matches!((a, b), (1, 2) | (2, 1)) || (a >= 3 && a == b)
+    }
+
+    fn merged_value(&self, a: u64, b: u64) -> u64 {
+        a + b
+    }
+
+    fn spawn_value(&self, random: u64) -> u64 {
+        random % SPAWN_VALUES + 1
+    }
+
+    /// The last cell of each line, the one farthest from the edge the move goes to
+    fn spawn_cells(&self, direction: Option<Direction>) -> [bool; 16] {
+        let Some(direction) = direction else {
+            return [true; 16];
+        };
+        let mut allowed = [false; 16];
+        for line in Game2048Engine::lines(direction) {
+            allowed[line[3]] = true;
+        }
+        allowed
+    }
+
+    /// A 1 and a 2 are the roots of x^2 - 3x + 2, and a tile from 3 up is not a root of
+    /// x(x - 1)(x - 2), both of which hold in the field without range checks
+    fn add_can_merge(&self, builder: &mut CircuitBuilder<F, D>, a: Target, b: Target) -> BoolTarget {
+        let zero = builder.zero();
+        let two = builder.two();
+        let three = builder.constant(F::from_canonical_u64(3));
+        let sum = builder.add(a, b);
+        let product = builder.mul(a, b);
+        let sum_is_three = builder.is_equal(sum, three);
+        let product_is_two = builder.is_equal(product, two);
+        let one_and_two = builder.and(sum_is_three, product_is_two);
+
+        let one = builder.one();
+        let a_minus_one = builder.sub(a, one);
+        let a_minus_two = builder.sub(a, two);
+        let small = builder.mul_many([a, a_minus_one, a_minus_two]);
+        let is_small = builder.is_equal(small, zero);
+        let is_large = builder.not(is_small);
+        let equal = builder.is_equal(a, b);
+        let equal_large = builder.and(equal, is_large);
+
+        builder.or(one_and_two, equal_large)
+    }
+
+    fn add_merged_value(&self, builder: &mut CircuitBuilder<F, D>, a: Target, b: Target) -> Target {
+        builder.add(a, b)
+    }
+
+    /// The tile is the entry at its index, given as a hint, among every valid tile
+    fn add_tile_check(&self, builder: &mut CircuitBuilder<F, D>, tile: Target) {
+        let index = builder.add_virtual_target();
+        builder.add_simple_generator(ThreesTileIndexGenerator { tile, index });
+        let tiles = (0..NUM_TILES)
+            .map(|index| builder.constant(F::from_canonical_u64(tile_at(index))))
+            .collect();
+        let selected = builder.random_access(index, tiles);
+        builder.connect(selected, tile);
+    }
+
+    fn add_spawn_value(&self, builder: &mut CircuitBuilder<F, D>, random: Target) -> Target {
+        let one = builder.one();
+        let three = builder.constant(F::from_canonical_u64(SPAWN_VALUES));
+        let (_, remainder) = Game2048Circuit::div_rem(builder, random, three);
+        builder.add(remainder, one)
+    }
+
+    /// Move the tiles behind the first cell that is empty or combines by one step
+    fn merge_row_scored(&self, row: [u64; 4]) -> ([u64; 4], u64) {
+        let Some(i) = (0..3).find(|&i| row[i] == 0 || self.can_merge(row[i], row[i + 1])) else {
+            return (row, 0);
+        };
+
+        let score = if row[i] == 0 { 0 } else { self.merge_score(row[i], row[i + 1]) };
+        let mut merged = [0; 4];
+        merged[..i].copy_from_slice(&row[..i]);
+        merged[i] = row[i] + row[i + 1];
+        merged[i + 1..3].copy_from_slice(&row[i + 2..]);
+        (merged, score)
+    }
+
+    /// In-circuit `merge_row_scored`
+    fn add_merge_row(&self, builder: &mut CircuitBuilder<F, D>, row: [Target; 4]) -> MergedRow {
+        let zero = builder.zero();
+
+        // moved_before[i]: a cell in front of cell i already moved, so cell i takes the tile behind it
+        let mut moved_before = [builder._false(); 4];
+        let mut moves_at = [builder._false(); 3];
+        let mut sums = [zero; 3];
+        let mut score = zero;
+        for i in 0..3 {
+            let is_empty = builder.is_equal(row[i], zero);
+            let merges = self.add_can_merge(builder, row[i], row[i + 1]);
+            let can_move = builder.or(is_empty, merges);
+            let not_moved = builder.not(moved_before[i]);
+            moves_at[i] = builder.and(can_move, not_moved);
+            moved_before[i + 1] = builder.or(moved_before[i], moves_at[i]);
+
+            sums[i] = self.add_merged_value(builder, row[i], row[i + 1]);
+            let merge_score = self.add_merge_score(builder, row[i], row[i + 1], sums[i]);
+            let scores = builder.and(moves_at[i], merges);
+            score = builder.mul_add(scores.target, merge_score, score);
+        }
+
+        // An empty cell takes the tile behind it unchanged, which is also their sum
+        let mut tiles = row;
+        for j in 0..4 {
+            let behind = row.get(j + 1).copied().unwrap_or(zero);
+            let stays = if j < 3 { builder._if(moves_at[j], sums[j], row[j]) } else { row[j] };
+            tiles[j] = builder._if(moved_before[j], behind, stays);
+        }
+        MergedRow { tiles, score }
+    }
+}
+
+/// Writes the index of a tile among the valid tiles, failing on any other tile
+#[derive(Debug, Default)]
+struct ThreesTileIndexGenerator {
+    tile: Target,
+    index: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for ThreesTileIndexGenerator {
+    fn id(&self) -> String {
+        "ThreesTileIndexGenerator".to_string()
+    }
+
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.tile]
+    }
+
+    fn run_once(&self, witness: &PartitionWitness<F>, out_buffer: &mut GeneratedValues<F>) -> Result<()> {
+        let tile = witness.get_target(self.tile).to_canonical_u64();
+        let index = (0..NUM_TILES)
+            .find(|&index| tile_at(index) == tile)
+            .ok_or_else(|| anyhow!("Tile {} is not a Threes tile up to {}", tile, MAX_THREES_TILE))?;
+        out_buffer.set_target(self.index, F::from_canonical_usize(index))
+    }
+
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target(self.tile)?;
+        dst.write_target(self.index)
+    }
+
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let tile = src.read_target()?;
+        let index = src.read_target()?;
+        Ok(Self { tile, index })
+    }
+}
@@ -0,0 +1,327 @@
+//! Trace circuit: a fixed number of consecutive moves of a seeded game in a single proof.
+//!
+//! Boards follow the game rules: a trace starting at move 0 starts from the opening
+//! board derived from the committed seed, and move n is followed by spawn number
+//! OPENING_TILES + n. A trace proof shows the moves are legal in the game the seed
+//! deals, for the player and session the seed was derived for.
+//!
+//! Moves are timestamped as in game circuits, at least the circuit's minimum interval
+//! apart, with the first and last timestamps public.
+
+use plonky2::field::types::{Field, PrimeField64};
+use plonky2::hash::hash_types::HashOut;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{ProverCircuitData, VerifierCircuitData};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+
+use super::{
+    Board, CircuitProfile, ClassicRules, Direction, Game2048Circuit, Game2048Engine, Game2048Error, LeaderboardStats,
+    LeaderboardTargets, OpeningTargets, PlayerEntropy, PlayerIdentity, RuleSet, ServerSeed, TimedMove, C, D, F,
+    IDENTITY_LEN, LEADERBOARD_LEN, OPENING_TILES,
+};
+
+/// Targets of consecutive moves of a seeded game, where boards[i + 1] follows boards[i]
+/// by directions[i] and its spawn
+#[derive(Clone, Debug)]
+pub struct TraceTargets {
+    /// The seed and the opening board derived from it
+    pub opening: OpeningTargets,
+    /// Index of the first move; boards[0] is the opening board when it is 0
+    pub start_move: Target,
+    /// The board before the first move, then the board after each move and its spawn
+    pub boards: Vec<[Target; 16]>,
+    /// Boards after each move, before its spawn
+    pub moved_boards: Vec<[Target; 16]>,
+    pub directions: Vec<Target>,
+    pub timestamps: Vec<Target>,
+    /// Smallest interval between consecutive timestamps
+    pub min_interval: u64,
+    /// Derived from the final board and the directions, public in `build_trace_circuit` only
+    pub leaderboard: LeaderboardTargets,
+}
+
+impl TraceTargets {
+    pub fn num_moves(&self) -> usize {
+        self.directions.len()
+    }
+
+    /// Assign the seed and the moves from the opening board, returning every board of the trace
+    ///
+    /// The boards are played under rules, which must be the rules the circuit was built with.
+    pub fn set_witness(
+        &self,
+        pw: &mut PartialWitness<F>,
+        rules: &dyn RuleSet,
+        server_seed: &ServerSeed,
+        player_entropy: &PlayerEntropy,
+        identity: &PlayerIdentity,
+        moves: &[TimedMove],
+    ) -> Result<Vec<Board>, Game2048Error> {
+        let seed = Game2048Engine::combine_seed(server_seed, player_entropy, identity);
+        let opening_board = rules.opening_board(&seed);
+        self.set_segment_witness(pw, rules, server_seed, player_entropy, identity, 0, &opening_board, moves)
+    }
+
+    /// Assign the seed and the moves from initial_board, the board at move start_move,
+    /// returning every board of the trace
+    ///
+    /// initial_board must be the seed's opening board when start_move is 0.
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_segment_witness(
+        &self,
+        pw: &mut PartialWitness<F>,
+        rules: &dyn RuleSet,
+        server_seed: &ServerSeed,
+        player_entropy: &PlayerEntropy,
+        identity: &PlayerIdentity,
+        start_move: u64,
+        initial_board: &Board,
+        moves: &[TimedMove],
+    ) -> Result<Vec<Board>, Game2048Error> {
+        if moves.len() != self.num_moves() {
+            return Err(Game2048Error::TraceLength { expected: self.num_moves(), len: moves.len() });
+        }
+        rules.validate_board(initial_board)?;
+        Game2048Engine::check_move_times(moves, self.min_interval)?;
+        let seed = Game2048Engine::combine_seed(server_seed, player_entropy, identity);
+        if start_move == 0 && *initial_board != rules.opening_board(&seed) {
+            return Err(Game2048Error::NotOpeningBoard);
+        }
+
+        self.opening.seed.set_witness(pw, server_seed, player_entropy, identity)?;
+        pw.set_target(self.start_move, F::from_canonical_u64(start_move))
+            .map_err(Game2048Error::WitnessConflict)?;
+        for (&target, &tile) in self.boards[0].iter().zip(initial_board) {
+            pw.set_target(target, F::from_canonical_u64(tile))
+                .map_err(Game2048Error::WitnessConflict)?;
+        }
+
+        let directions: Vec<_> = moves.iter().map(|timed_move| timed_move.direction).collect();
+        let boards = rules.play_from(&seed, initial_board, start_move, &directions);
+        for (n, timed_move) in moves.iter().enumerate() {
+            let moved_board = rules.apply_move(&boards[n], timed_move.direction);
+            for (&target, &tile) in self.moved_boards[n].iter().zip(&moved_board) {
+                pw.set_target(target, F::from_canonical_u64(tile))
+                    .map_err(Game2048Error::WitnessConflict)?;
+            }
+            pw.set_target(self.directions[n], F::from_canonical_u64(timed_move.direction.as_u64()))
+                .map_err(Game2048Error::WitnessConflict)?;
+            pw.set_target(self.timestamps[n], F::from_canonical_u64(timed_move.timestamp))
+                .map_err(Game2048Error::WitnessConflict)?;
+        }
+        Ok(boards)
+    }
+}
+
+impl Game2048Circuit {
+    /// Build a circuit for the first num_moves moves of a game, at least min_interval
+    /// ticks apart
+    ///
+    /// Public inputs are the server's seed commitment (4), the player entropy (4),
+    /// the player identity (5), the final board (16), the directions (num_moves), the
+    /// first and last timestamps (2) and the leaderboard fields (LEADERBOARD_LEN); the
+    /// boards before the final one stay private.
+    pub fn build_trace_circuit(
+        num_moves: usize,
+        min_interval: u64,
+        profile: CircuitProfile,
+    ) -> Result<(CircuitBuilder<F, D>, TraceTargets), Game2048Error> {
+        Self::build_trace_circuit_with_rules(num_moves, min_interval, profile, &ClassicRules)
+    }
+
+    /// Build the trace circuit for a 2048 variant, whose rules decide the moves and the spawns
+    pub fn build_trace_circuit_with_rules(
+        num_moves: usize,
+        min_interval: u64,
+        profile: CircuitProfile,
+        rules: &dyn RuleSet,
+    ) -> Result<(CircuitBuilder<F, D>, TraceTargets), Game2048Error> {
+        if num_moves == 0 {
+            return Err(Game2048Error::EmptyTrace);
+        }
+        let mut builder = CircuitBuilder::<F, D>::new(profile.config());
+        let targets = Self::add_trace(&mut builder, rules, num_moves, min_interval);
+        builder.assert_zero(targets.start_move);
+
+        builder.register_public_inputs(&targets.opening.seed.server_commitment.elements);
+        builder.register_public_inputs(&targets.opening.seed.player_entropy);
+        builder.register_public_inputs(&targets.opening.seed.identity.targets());
+        builder.register_public_inputs(&targets.boards[num_moves]);
+        builder.register_public_inputs(&targets.directions);
+        builder.register_public_input(targets.timestamps[0]);
+        builder.register_public_input(targets.timestamps[num_moves - 1]);
+        builder.register_public_inputs(&targets.leaderboard.targets());
+
+        Ok((builder, targets))
+    }
+
+    /// Add unregistered targets for num_moves moves of a seeded game from move start_move
+    ///
+    /// The initial board is tied to the opening board derived from the seed when
+    /// start_move is 0, and left to the caller otherwise, e.g. to a commitment made by
+    /// the previous segment of the game.
+    pub(crate) fn add_trace(
+        builder: &mut CircuitBuilder<F, D>,
+        rules: &dyn RuleSet,
+        num_moves: usize,
+        min_interval: u64,
+    ) -> TraceTargets {
+        let opening = Self::add_opening(builder, rules);
+        let start_move = builder.add_virtual_target();
+        let initial_board = builder.add_virtual_target_arr::<16>();
+        let moved_boards: Vec<_> = (0..num_moves).map(|_| builder.add_virtual_target_arr::<16>()).collect();
+        let directions = builder.add_virtual_targets(num_moves);
+        let timestamps = Self::add_timestamps(builder, num_moves, min_interval);
+
+        // is_first * (initial_board - opening board) = 0
+        let zero = builder.zero();
+        let is_first = builder.is_equal(start_move, zero);
+        for (&tile, &opening_tile) in initial_board.iter().zip(&opening.board) {
+            let difference = builder.sub(tile, opening_tile);
+            let forced = builder.mul(is_first.target, difference);
+            builder.assert_zero(forced);
+        }
+
+        let mut boards = vec![initial_board];
+        for (n, (moved_board, &direction)) in moved_boards.iter().zip(&directions).enumerate() {
+            let board = boards[n];
+            Self::add_rule_constraints(builder, rules, &board, moved_board, direction);
+            let spawn_index = builder.add_const(start_move, F::from_canonical_u64(OPENING_TILES + n as u64));
+            let seed = opening.seed.seed;
+            boards.push(Self::add_spawn_with_rules(builder, rules, moved_board, seed, spawn_index, Some(direction)));
+        }
+
+        let leaderboard = Self::add_leaderboard(builder, &boards[num_moves], &directions);
+        TraceTargets { opening, start_move, boards, moved_boards, directions, timestamps, min_interval, leaderboard }
+    }
+}
+
+/// Index of the final board in a trace proof's public inputs
+const BOARD_START: usize = 8 + IDENTITY_LEN;
+/// Index of the directions, after the final board
+const DIRECTIONS_START: usize = BOARD_START + 16;
+
+/// A proof of the first moves of a seeded game, with the seed commitment, the player
+/// entropy and identity, the final board, the directions and the first and last
+/// timestamps as public inputs
+#[derive(Clone, Debug)]
+pub struct Game2048TraceProof {
+    pub proof: ProofWithPublicInputs<F, C, D>,
+}
+
+impl Game2048TraceProof {
+    pub fn server_commitment(&self) -> HashOut<F> {
+        HashOut::from_partial(&self.proof.public_inputs[0..4])
+    }
+
+    pub fn player_entropy(&self) -> PlayerEntropy {
+        self.proof.public_inputs[4..8].try_into().unwrap()
+    }
+
+    pub fn player_identity(&self) -> PlayerIdentity {
+        PlayerIdentity::from_elements(&self.proof.public_inputs[8..BOARD_START])
+    }
+
+    pub fn final_board(&self) -> Board {
+        let mut board = [0; 16];
+        for (tile, input) in board.iter_mut().zip(&self.proof.public_inputs[BOARD_START..DIRECTIONS_START]) {
+            *tile = input.to_canonical_u64();
+        }
+        board
+    }
+
+    pub fn directions(&self) -> Vec<Option<Direction>> {
+        self.proof.public_inputs[DIRECTIONS_START..self.leaderboard_start() - 2]
+            .iter()
+            .map(|input| Direction::from_u64(input.to_canonical_u64()))
+            .collect()
+    }
+
+    pub fn first_timestamp(&self) -> u64 {
+        self.proof.public_inputs[self.leaderboard_start() - 2].to_canonical_u64()
+    }
+
+    pub fn last_timestamp(&self) -> u64 {
+        self.proof.public_inputs[self.leaderboard_start() - 1].to_canonical_u64()
+    }
+
+    pub fn leaderboard_stats(&self) -> LeaderboardStats {
+        LeaderboardStats::from_elements(&self.proof.public_inputs[self.leaderboard_start()..])
+    }
+
+    fn leaderboard_start(&self) -> usize {
+        self.proof.public_inputs.len() - LEADERBOARD_LEN
+    }
+
+    /// Check that the moves were played with the seed the server published a commitment to
+    pub fn check_server_commitment(&self, published: &HashOut<F>) -> Result<(), Game2048Error> {
+        if self.server_commitment() != *published {
+            return Err(Game2048Error::SeedCommitmentMismatch);
+        }
+        Ok(())
+    }
+
+    /// Check that the proof was made for this player and session
+    pub fn check_player_identity(&self, expected: &PlayerIdentity) -> Result<(), Game2048Error> {
+        if self.player_identity() != *expected {
+            return Err(Game2048Error::IdentityMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Proves traces of a fixed number of moves
+pub struct Game2048TraceProver {
+    circuit: ProverCircuitData<F, C, D>,
+    verifier: VerifierCircuitData<F, C, D>,
+    targets: TraceTargets,
+    rules: Box<dyn RuleSet>,
+}
+
+impl Game2048TraceProver {
+    pub fn new(num_moves: usize, min_interval: u64, profile: CircuitProfile) -> Result<Self, Game2048Error> {
+        Self::with_rules(num_moves, min_interval, profile, ClassicRules)
+    }
+
+    /// Prover for traces of a 2048 variant
+    pub fn with_rules(
+        num_moves: usize,
+        min_interval: u64,
+        profile: CircuitProfile,
+        rules: impl RuleSet + 'static,
+    ) -> Result<Self, Game2048Error> {
+        let (builder, targets) =
+            Game2048Circuit::build_trace_circuit_with_rules(num_moves, min_interval, profile, &rules)?;
+        let circuit = builder.build::<C>();
+        let verifier = circuit.verifier_data();
+        Ok(Self { circuit: circuit.prover_data(), verifier, targets, rules: Box::new(rules) })
+    }
+
+    /// Prove the first moves of the game this player plays with this seed
+    pub fn prove(
+        &self,
+        server_seed: &ServerSeed,
+        player_entropy: &PlayerEntropy,
+        identity: &PlayerIdentity,
+        moves: &[TimedMove],
+    ) -> Result<Game2048TraceProof, Game2048Error> {
+        let mut pw = PartialWitness::<F>::new();
+        self.targets
+            .set_witness(&mut pw, self.rules.as_ref(), server_seed, player_entropy, identity, moves)?;
+        let proof = self.circuit.prove(pw).map_err(Game2048Error::ProofFailure)?;
+        Ok(Game2048TraceProof { proof })
+    }
+
+    /// Verifier data of the trace circuit, e.g. to build a `Game2048Compressor`
+    pub fn verifier_data(&self) -> &VerifierCircuitData<F, C, D> {
+        &self.verifier
+    }
+
+    pub fn verify(&self, proof: &Game2048TraceProof) -> Result<(), Game2048Error> {
+        self.verifier
+            .verify(proof.proof.clone())
+            .map_err(Game2048Error::ProofFailure)
+    }
+}
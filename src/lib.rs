@@ -1 +1,9 @@
-pub mod game2048;
\ No newline at end of file
+pub mod game2048;
+
+pub use game2048::{
+    Board, BoardHistory, CellMismatch, CircuitProfile, CircuitStats, ClassicRules, CommittedTraceProof,
+    CommittedTraceProver, CompressedProof, Direction, FibonacciRules, Game2048Circuit, Game2048Compressor,
+    Game2048Engine, Game2048Error, Game2048GameProof, Game2048GameProver, Game2048Proof, Game2048Prover,
+    Game2048Targets, Game2048TraceProof, Game2048TraceProver, Game2048Verifier, HistoryInclusion, LeaderboardStats,
+    Line, PlayerEntropy, PlayerIdentity, RuleSet, Salt, ServerSeed, ThreesRules, TimedMove,
+};
@@ -1,51 +1,60 @@
-mod game2048;
-
-use game2048::{Game2048Circuit, F};
+use game2048_plonky2::game2048::F;
+use game2048_plonky2::{Direction, Game2048Circuit, Game2048Compressor, Game2048Prover, PlayerIdentity};
 use plonky2::field::types::Field;
-use plonky2::iop::witness::{PartialWitness, WitnessWrite};
-use plonky2::plonk::config::PoseidonGoldilocksConfig;
 
 fn main() {
+    let command = std::env::args().nth(1);
+
+    // `stats` reports the size of the move circuit instead of proving the sample move
+    if command.as_deref() == Some("stats") {
+        match Game2048Circuit::circuit_stats() {
+            Ok(stats) => println!("{}", stats),
+            Err(err) => eprintln!("Failed to measure circuit: {}", err),
+        }
+        return;
+    }
+
     // Input boards and direction
-    let before_board: Vec<F> = vec![
-        F::from_canonical_u32(2), F::from_canonical_u32(2), F::from_canonical_u32(4), F::from_canonical_u32(8),
-        F::from_canonical_u32(2), F::from_canonical_u32(0), F::from_canonical_u32(4), F::from_canonical_u32(4),
-        F::from_canonical_u32(2), F::from_canonical_u32(2), F::from_canonical_u32(2), F::from_canonical_u32(4),
-        F::from_canonical_u32(0), F::from_canonical_u32(2), F::from_canonical_u32(4), F::from_canonical_u32(4),
+    let before_board = [
+        2, 2, 4, 8, //
+        2, 0, 4, 4, //
+        2, 2, 2, 4, //
+        0, 2, 4, 4, //
     ];
-
-    let after_board: Vec<F> = vec![
-        F::from_canonical_u32(0), F::from_canonical_u32(4), F::from_canonical_u32(4), F::from_canonical_u32(8),
-        F::from_canonical_u32(0), F::from_canonical_u32(0), F::from_canonical_u32(2), F::from_canonical_u32(8),
-        F::from_canonical_u32(0), F::from_canonical_u32(2), F::from_canonical_u32(4), F::from_canonical_u32(4),
-        F::from_canonical_u32(0), F::from_canonical_u32(0), F::from_canonical_u32(2), F::from_canonical_u32(8),
+    let after_board = [
+        0, 4, 4, 8, //
+        0, 0, 2, 8, //
+        0, 2, 4, 4, //
+        0, 0, 2, 8, //
     ];
 
-    // Direction: "right"
-    let direction = F::from_canonical_u32(3); // 0 for "right", as defined in the circuit
-
-    // Build the circuit
-    let (circuit_builder, targets) = Game2048Circuit::build_circuit();
-
-    // Unpack targets
-    let (before_targets, after_targets, direction_target) =
-        (&targets[0..16], &targets[16..32], targets[32]);
-
-    // Set the witness values
-    let mut pw = PartialWitness::<F>::new();
-    for (i, &target) in before_targets.iter().enumerate() {
-        pw.set_target(target, before_board[i]).unwrap();
-    }
-    for (i, &target) in after_targets.iter().enumerate() {
-        pw.set_target(target, after_board[i]).unwrap();
+    // A sample player's public key, in its first session
+    let identity = PlayerIdentity::new(&[F::from_canonical_u64(1)], 0);
+
+    // Build the circuit, then prove and verify the move
+    let prover = Game2048Prover::new();
+    let verifier = prover.verifier();
+    let proof = match prover.prove(&before_board, &after_board, Direction::Right, &identity) {
+        Ok(proof) => proof,
+        Err(err) => return println!("Proof verified: false ({})", err),
+    };
+    match verifier.verify(&proof) {
+        Ok(()) => println!("Proof verified: true"),
+        Err(err) => return println!("Proof verified: false ({})", err),
     }
-    pw.set_target(direction_target, direction).unwrap();
 
-    // Build and generate the proof
-    let circuit = circuit_builder.build::<PoseidonGoldilocksConfig>();
-    let proof = circuit.prove(pw).unwrap();
-
-    // Verify the proof
-    let verified = circuit.verify(proof).is_ok();
-    println!("Proof verified: {}", verified);
-}
\ No newline at end of file
+    // `compress` also wraps the proof into a compact one and reports both sizes
+    if command.as_deref() == Some("compress") {
+        let compressor = Game2048Compressor::new(verifier.verifier_data());
+        let compressed = compressor
+            .compress(&proof.proof)
+            .and_then(|compressed| compressor.verify(&compressed).map(|()| compressed));
+        match compressed {
+            Ok(compressed) => {
+                println!("Proof size: {} bytes", proof.to_bytes().len());
+                println!("Compressed proof size: {} bytes", compressed.to_bytes().len());
+            }
+            Err(err) => eprintln!("Failed to compress proof: {}", err),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+#[path = "../benches/per_direction/mod.rs"]
+mod per_direction;
+
+use game2048_plonky2::game2048::{CircuitStats, Game2048Circuit};
+
+// Regression bounds just above the current circuit (62 gates, degree_bits 6,
+// ~90 KB proofs), so any real growth fails.
+const MAX_GATES: usize = 64;
+const MAX_DEGREE_BITS: usize = 6;
+const MAX_PROOF_SIZE: usize = 100_000;
+
+#[test]
+fn move_circuit_size_regression() {
+    let stats = Game2048Circuit::circuit_stats().unwrap();
+    assert!(stats.num_gates <= MAX_GATES, "move circuit grew:\n{stats}");
+    assert!(stats.degree_bits <= MAX_DEGREE_BITS, "move circuit grew:\n{stats}");
+    assert!(stats.proof_size <= MAX_PROOF_SIZE, "move proofs grew:\n{stats}");
+    assert_eq!(stats.num_public_inputs, 33);
+    assert_eq!(stats.gate_counts.values().sum::<usize>(), 1 << stats.degree_bits);
+}
+
+#[test]
+fn orienting_the_board_shrinks_the_move_circuit() {
+    let per_direction = CircuitStats::measure(per_direction::build_per_direction_circuit()).unwrap();
+    let stats = Game2048Circuit::circuit_stats().unwrap();
+    println!("per-direction merges: {} gates, degree_bits {}", per_direction.num_gates, per_direction.degree_bits);
+    println!("oriented board: {} gates, degree_bits {}", stats.num_gates, stats.degree_bits);
+    assert!(stats.num_gates * 2 < per_direction.num_gates, "{stats}\n{per_direction}");
+    assert!(stats.degree_bits < per_direction.degree_bits, "{stats}\n{per_direction}");
+}
+
+#[test]
+fn lookup_circuit_is_dominated_by_its_merge_table() {
+    let stats = Game2048Circuit::lookup_circuit_stats().unwrap();
+    let table_rows: usize = stats
+        .gate_counts
+        .iter()
+        .filter(|(id, _)| id.starts_with("LookupTableGate"))
+        .map(|(_, &count)| count)
+        .sum();
+    assert!(table_rows > stats.num_gates * 9 / 10, "{stats}");
+    assert_eq!(stats.num_public_inputs, 33);
+}
@@ -0,0 +1,34 @@
+use game2048_plonky2::game2048::{F, IDENTITY_LEN};
+use game2048_plonky2::{
+    CircuitProfile, Direction, Game2048Compressor, Game2048Engine, Game2048TraceProver, PlayerIdentity, ServerSeed,
+    TimedMove,
+};
+use plonky2::field::types::Field;
+
+#[test]
+fn compressed_trace_proof_is_smaller_and_keeps_public_inputs() {
+    let moves = [
+        TimedMove { direction: Direction::Left, timestamp: 1_000 },
+        TimedMove { direction: Direction::Down, timestamp: 1_100 },
+    ];
+    let prover = Game2048TraceProver::new(moves.len(), 0, CircuitProfile::Recursion).unwrap();
+    let identity = PlayerIdentity::new(&[F::from_canonical_u64(42)], 1);
+    let player_entropy = [11, 12, 13, 14].map(F::from_canonical_u64);
+    let proof = prover.prove(&ServerSeed::random(), &player_entropy, &identity, &moves).unwrap();
+
+    let compressor = Game2048Compressor::new(prover.verifier_data());
+    let compressed = compressor.compress(&proof.proof).unwrap();
+    compressor.verify(&compressed).unwrap();
+
+    assert_eq!(compressed.public_inputs(), proof.proof.public_inputs.as_slice());
+    assert!(compressed.to_bytes().len() < proof.proof.to_bytes().len());
+
+    // The forwarded public inputs are bound to the inner proof
+    let mut forged = compressed.clone();
+    let final_board = Game2048Engine::apply_move(&proof.final_board(), Direction::Up);
+    for (input, tile) in forged.proof.public_inputs[8 + IDENTITY_LEN..24 + IDENTITY_LEN].iter_mut().zip(final_board) {
+        *input = F::from_canonical_u64(tile);
+    }
+    assert_ne!(forged.public_inputs(), compressed.public_inputs());
+    assert!(compressor.verify(&forged).is_err());
+}
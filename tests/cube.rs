@@ -0,0 +1,116 @@
+use game2048_plonky2::game2048::{Cube, CubeDirection, CubeProver, C, F};
+use game2048_plonky2::{CircuitProfile, Direction, Game2048Circuit, Game2048Engine, Game2048Error};
+use plonky2::field::types::Field;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+
+/// A cube with tiles in several layers, so that every direction moves something
+fn sample_cube() -> Cube {
+    let mut cube = [0; 64];
+    for (cell, tile) in [(0, 2), (1, 2), (4, 2), (16, 2), (21, 4), (37, 4), (42, 8), (63, 8), (47, 8)] {
+        cube[cell] = tile;
+    }
+    cube
+}
+
+#[test]
+fn cube_lines_cover_the_cube_along_each_axis() {
+    for direction in CubeDirection::ALL {
+        let mut cells: Vec<usize> = Game2048Engine::cube_lines(direction).into_iter().flatten().collect();
+        cells.sort_unstable();
+        assert_eq!(cells, (0..64).collect::<Vec<_>>(), "{direction:?}");
+    }
+
+    // The front layer moves like a board within it
+    let board = [2, 2, 4, 8, 2, 0, 4, 4, 2, 2, 2, 4, 0, 2, 4, 4];
+    let mut cube = [0; 64];
+    cube[..16].copy_from_slice(&board);
+    for (cube_direction, direction) in [
+        (CubeDirection::Up, Direction::Up),
+        (CubeDirection::Down, Direction::Down),
+        (CubeDirection::Left, Direction::Left),
+        (CubeDirection::Right, Direction::Right),
+    ] {
+        let after = Game2048Engine::apply_cube_move(&cube, cube_direction);
+        assert_eq!(after[..16], Game2048Engine::apply_move(&board, direction));
+    }
+
+    // Front and back slide along z: cells 0 and 16 merge into the front or back layer
+    assert_eq!(Game2048Engine::apply_cube_move(&sample_cube(), CubeDirection::Front)[0], 4);
+    assert_eq!(Game2048Engine::apply_cube_move(&sample_cube(), CubeDirection::Back)[48], 4);
+}
+
+#[test]
+fn every_cube_direction_is_proven() {
+    let (builder, targets) = Game2048Circuit::build_cube_circuit(CircuitProfile::Recursion);
+    let data = builder.build::<C>();
+    let cube = sample_cube();
+
+    for direction in CubeDirection::ALL {
+        let after_cube = Game2048Engine::apply_cube_move(&cube, direction);
+        assert_ne!(after_cube, cube);
+        Game2048Engine::check_cube_move(&cube, &after_cube, direction).unwrap();
+
+        let mut pw = PartialWitness::<F>::new();
+        targets.set_witness(&mut pw, &cube, &after_cube, direction).unwrap();
+        let proof = data.prove(pw).unwrap();
+        data.verify(proof.clone()).unwrap();
+        assert_eq!(proof.public_inputs[128], F::from_canonical_u64(direction.as_u64()));
+    }
+}
+
+#[test]
+fn wrong_moves_and_directions_are_rejected() {
+    let (builder, targets) = Game2048Circuit::build_cube_circuit(CircuitProfile::Recursion);
+    let data = builder.build::<C>();
+    let cube = sample_cube();
+
+    // A left move claimed as a front move, reported cell by cell
+    let after_cube = Game2048Engine::apply_cube_move(&cube, CubeDirection::Left);
+    let Err(Game2048Error::IllegalCubeMove { direction: CubeDirection::Front, mismatches }) =
+        Game2048Engine::check_cube_move(&cube, &after_cube, CubeDirection::Front)
+    else {
+        panic!("left move accepted as a front move");
+    };
+    let expected_cube = Game2048Engine::apply_cube_move(&cube, CubeDirection::Front);
+    assert_eq!(mismatches.len(), (0..64).filter(|&cell| expected_cube[cell] != after_cube[cell]).count());
+    for mismatch in &mismatches {
+        let cell = mismatch.z * 16 + mismatch.y * 4 + mismatch.x;
+        assert_eq!((mismatch.expected, mismatch.actual), (expected_cube[cell], after_cube[cell]));
+    }
+    let mut pw = PartialWitness::<F>::new();
+    targets.set_witness(&mut pw, &cube, &after_cube, CubeDirection::Front).unwrap();
+    assert!(data.prove(pw).is_err());
+
+    // Direction values 6 and 7 would still select a line layout without the range check
+    for direction in [6, 7] {
+        let mut forged = PartialWitness::<F>::new();
+        for (&target, &tile) in targets.before_cube.iter().zip(&cube).chain(targets.after_cube.iter().zip(&cube)) {
+            forged.set_target(target, F::from_canonical_u64(tile)).unwrap();
+        }
+        forged.set_target(targets.direction, F::from_canonical_u64(direction)).unwrap();
+        assert!(data.prove(forged).is_err(), "direction {direction} accepted");
+    }
+}
+
+#[test]
+fn cube_prover_checks_moves_before_proving() {
+    let prover = CubeProver::new(CircuitProfile::Recursion);
+    let cube = sample_cube();
+    let after_cube = Game2048Engine::apply_cube_move(&cube, CubeDirection::Back);
+    let proof = prover.prove(&cube, &after_cube, CubeDirection::Back).unwrap();
+    prover.verify(&proof).unwrap();
+    assert_eq!(proof.before_cube(), cube);
+    assert_eq!(proof.after_cube(), after_cube);
+    assert_eq!(proof.direction(), Some(CubeDirection::Back));
+
+    assert!(matches!(
+        prover.prove(&cube, &after_cube, CubeDirection::Front),
+        Err(Game2048Error::IllegalCubeMove { direction: CubeDirection::Front, .. })
+    ));
+    let mut invalid = cube;
+    invalid[5] = 3;
+    assert!(matches!(
+        prover.prove(&invalid, &after_cube, CubeDirection::Back),
+        Err(Game2048Error::InvalidTile { cell: 5, value: 3 })
+    ));
+}
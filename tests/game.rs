@@ -0,0 +1,187 @@
+use game2048_plonky2::game2048::{CircuitProfile, Direction, Game2048Circuit, Game2048Engine, C, F};
+use game2048_plonky2::{
+    BoardHistory, ClassicRules, Game2048Error, Game2048GameProver, PlayerEntropy, PlayerIdentity, ServerSeed, TimedMove,
+};
+use plonky2::field::types::{Field, Field64, PrimeField64};
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+
+const DIRECTIONS: [Direction; 4] = [Direction::Left, Direction::Down, Direction::Right, Direction::Up];
+
+/// Minimum ticks between moves, e.g. milliseconds
+const MIN_INTERVAL: u64 = 100;
+
+fn timed_moves(timestamps: [u64; 4]) -> Vec<TimedMove> {
+    DIRECTIONS
+        .iter()
+        .zip(timestamps)
+        .map(|(&direction, timestamp)| TimedMove { direction, timestamp })
+        .collect()
+}
+
+fn player_entropy() -> PlayerEntropy {
+    [11, 12, 13, 14].map(F::from_canonical_u64)
+}
+
+fn identity() -> PlayerIdentity {
+    PlayerIdentity::new(&[F::from_canonical_u64(42)], 1)
+}
+
+#[test]
+fn opening_board_has_two_tiles_from_the_seed() {
+    let server_seed = ServerSeed::random();
+    let seed = Game2048Engine::combine_seed(&server_seed, &player_entropy(), &identity());
+    let opening_board = Game2048Engine::opening_board(&seed);
+    assert_eq!(opening_board.iter().filter(|&&tile| tile != 0).count(), 2);
+
+    let (builder, targets) = Game2048Circuit::build_opening_circuit(CircuitProfile::Recursion);
+    let data = builder.build::<C>();
+    let mut pw = PartialWitness::<F>::new();
+    targets.seed.set_witness(&mut pw, &server_seed, &player_entropy(), &identity()).unwrap();
+    let proof = data.prove(pw).unwrap();
+    data.verify(proof.clone()).unwrap();
+
+    let public_board: Vec<u64> = proof.public_inputs[13..29].iter().map(|input| input.to_canonical_u64()).collect();
+    assert_eq!(public_board, opening_board);
+}
+
+#[test]
+fn game_proof_starts_from_the_seeded_opening_board() {
+    let prover = Game2048GameProver::new(DIRECTIONS.len(), MIN_INTERVAL, CircuitProfile::Recursion).unwrap();
+    let server_seed = ServerSeed::random();
+    let moves = timed_moves([1_000, 1_100, 1_450, 2_000]);
+    let proof = prover.prove(&server_seed, &player_entropy(), &identity(), &moves).unwrap();
+    prover.verify(&proof).unwrap();
+
+    let seed = Game2048Engine::combine_seed(&server_seed, &player_entropy(), &identity());
+    let boards = Game2048Engine::play(&seed, &DIRECTIONS);
+    assert_eq!(proof.final_board(), boards[DIRECTIONS.len()]);
+    assert_eq!(proof.directions(), DIRECTIONS.map(Some));
+    assert_eq!(proof.player_entropy(), player_entropy());
+    proof.check_player_identity(&identity()).unwrap();
+    assert_eq!((proof.first_timestamp(), proof.last_timestamp()), (1_000, 2_000));
+    let stats = proof.leaderboard_stats();
+    assert_eq!(stats, Game2048Engine::leaderboard_stats(&boards[DIRECTIONS.len()], &DIRECTIONS));
+    assert_eq!((stats.num_moves, stats.direction_counts), (4, [1, 1, 1, 1]));
+    proof.check_server_commitment(&server_seed.commitment()).unwrap();
+    assert!(matches!(
+        proof.check_server_commitment(&ServerSeed::random().commitment()),
+        Err(Game2048Error::SeedCommitmentMismatch)
+    ));
+
+    // The final board is bound to the seed: claiming another one fails
+    let mut forged = proof.clone();
+    forged.proof.public_inputs[13] += F::TWO;
+    assert!(prover.verify(&forged).is_err());
+
+    // So is the identity: replaying the proof as another player fails
+    let other_player = PlayerIdentity::new(&[F::from_canonical_u64(43)], 1);
+    assert!(matches!(proof.check_player_identity(&other_player), Err(Game2048Error::IdentityMismatch)));
+    let mut replayed = proof.clone();
+    replayed.proof.public_inputs[8..13].copy_from_slice(&other_player.elements());
+    assert!(prover.verify(&replayed).is_err());
+}
+
+#[test]
+fn moves_faster_than_the_minimum_interval_are_rejected() {
+    let (builder, targets) =
+        Game2048Circuit::build_game_circuit(DIRECTIONS.len(), MIN_INTERVAL, CircuitProfile::Recursion).unwrap();
+    let data = builder.build::<C>();
+    let server_seed = ServerSeed::random();
+
+    let mut pw = PartialWitness::<F>::new();
+    let too_fast = timed_moves([1_000, 1_099, 1_450, 2_000]);
+    assert!(matches!(
+        targets.set_witness(&mut pw, &ClassicRules, &server_seed, &player_entropy(), &identity(), &too_fast),
+        Err(Game2048Error::MoveTooSoon { move_index: 1, timestamp: 1_099, previous: 1_000, min_interval: MIN_INTERVAL })
+    ));
+    let decreasing = timed_moves([1_000, 1_100, 1_450, 1_400]);
+    assert!(matches!(
+        targets.set_witness(&mut pw, &ClassicRules, &server_seed, &player_entropy(), &identity(), &decreasing),
+        Err(Game2048Error::TimestampNotIncreasing { move_index: 3, timestamp: 1_400, previous: 1_450 })
+    ));
+    let too_long = timed_moves([1_000, 1_100, 1_450, 1_450 + MIN_INTERVAL + (1 << 32)]);
+    assert!(matches!(
+        targets.set_witness(&mut pw, &ClassicRules, &server_seed, &player_entropy(), &identity(), &too_long),
+        Err(Game2048Error::MoveGapTooLong { move_index: 3, previous: 1_450, .. })
+    ));
+    let late_start = [1 << 32, (1 << 32) + 100, (1 << 32) + 450, (1 << 32) + 1_000];
+    let late_moves = timed_moves(late_start);
+    assert!(matches!(
+        targets.set_witness(&mut pw, &ClassicRules, &server_seed, &player_entropy(), &identity(), &late_moves),
+        Err(Game2048Error::TimestampOutOfRange { move_index: 0, timestamp: 0x1_0000_0000 })
+    ));
+    let past_the_field = timed_moves([1_000, 1_100, 1_450, u64::MAX]);
+    assert!(matches!(
+        targets.set_witness(&mut pw, &ClassicRules, &server_seed, &player_entropy(), &identity(), &past_the_field),
+        Err(Game2048Error::TimestampOutOfRange { move_index: 3, timestamp: u64::MAX })
+    ));
+    assert!(matches!(
+        Game2048Circuit::build_game_circuit(0, MIN_INTERVAL, CircuitProfile::Recursion),
+        Err(Game2048Error::EmptyTrace)
+    ));
+
+    // Bypass the native check: the circuit rejects a decreasing and a too-short interval, a
+    // first timestamp from 2^32 on, and one just below the field order that wraps around to
+    // the next
+    let wrapping = [F::ORDER - 50, 50, 400, 1_000];
+    let trace = &targets.trace;
+    let seed = Game2048Engine::combine_seed(&server_seed, &player_entropy(), &identity());
+    let boards = Game2048Engine::play(&seed, &DIRECTIONS);
+    for timestamps in [[1_000, 1_100, 1_450, 1_400], [1_000, 1_099, 1_450, 2_000], late_start, wrapping] {
+        let mut forged = PartialWitness::<F>::new();
+        trace.opening.seed.set_witness(&mut forged, &server_seed, &player_entropy(), &identity()).unwrap();
+        forged.set_target(trace.start_move, F::ZERO).unwrap();
+        for (&target, &tile) in trace.boards[0].iter().zip(&boards[0]) {
+            forged.set_target(target, F::from_canonical_u64(tile)).unwrap();
+        }
+        for (n, timed_move) in timed_moves(timestamps).iter().enumerate() {
+            let moved_board = Game2048Engine::apply_move(&boards[n], timed_move.direction);
+            for (&target, &tile) in trace.moved_boards[n].iter().zip(&moved_board) {
+                forged.set_target(target, F::from_canonical_u64(tile)).unwrap();
+            }
+            forged.set_target(trace.directions[n], F::from_canonical_u64(timed_move.direction.as_u64())).unwrap();
+            forged.set_target(trace.timestamps[n], F::from_canonical_u64(timed_move.timestamp)).unwrap();
+        }
+        assert!(data.prove(forged).is_err(), "timestamps {timestamps:?} accepted");
+    }
+}
+
+#[test]
+fn history_root_proves_which_board_occurred_at_each_move() {
+    let prover = Game2048GameProver::new(DIRECTIONS.len(), MIN_INTERVAL, CircuitProfile::Recursion).unwrap();
+    let server_seed = ServerSeed::random();
+    let moves = timed_moves([1_000, 1_100, 1_450, 2_000]);
+    let proof = prover.prove(&server_seed, &player_entropy(), &identity(), &moves).unwrap();
+    prover.verify(&proof).unwrap();
+
+    let seed = Game2048Engine::combine_seed(&server_seed, &player_entropy(), &identity());
+    let boards = Game2048Engine::play(&seed, &DIRECTIONS);
+    let history = BoardHistory::new(&boards, &DIRECTIONS).unwrap();
+    assert_eq!(proof.history_root(), history.root());
+
+    // Every board, including the final one with no move after it, is in the history
+    for (move_index, board) in boards.iter().enumerate() {
+        let inclusion = history.prove_inclusion(move_index).unwrap();
+        assert_eq!(inclusion.board, *board);
+        assert_eq!(inclusion.direction, DIRECTIONS.get(move_index).copied());
+        inclusion.verify(&proof.history_root()).unwrap();
+    }
+    assert!(history.prove_inclusion(DIRECTIONS.len() + 1).is_none());
+
+    // Another board, another move or another index is not
+    let inclusion = history.prove_inclusion(2).unwrap();
+    let mut other_board = inclusion.clone();
+    other_board.board = boards[1];
+    let mut other_move = inclusion.clone();
+    other_move.direction = Some(Direction::Left);
+    let mut other_index = inclusion.clone();
+    other_index.move_index = 3;
+    for forged in [other_board, other_move, other_index] {
+        assert!(matches!(forged.verify(&proof.history_root()), Err(Game2048Error::HistoryMismatch { .. })));
+    }
+
+    // The root is bound to the game: claiming another one fails
+    let mut forged = proof.clone();
+    forged.proof.public_inputs[29] += F::ONE;
+    assert!(prover.verify(&forged).is_err());
+}
@@ -0,0 +1,150 @@
+use std::sync::OnceLock;
+
+use game2048_plonky2::game2048::{Board, Direction, Game2048Circuit, Game2048Engine, Game2048Targets, C, D, F};
+use plonky2::field::types::{Field, PrimeField64};
+use plonky2::iop::generator::generate_partial_witness;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+use proptest::prelude::*;
+
+/// Tile values enumerated exhaustively for single rows
+const ALPHABET: [u64; 5] = [0, 2, 4, 8, 16];
+
+/// A circuit that only runs `merge_2048_row`, so outputs can be read back from the witness
+struct RowCircuit {
+    data: CircuitData<F, C, D>,
+    inputs: [Target; 4],
+    outputs: [Target; 4],
+}
+
+impl RowCircuit {
+    fn build() -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let inputs = [(); 4].map(|_| builder.add_virtual_target());
+        let outputs = Game2048Circuit::merge_2048_row(&mut builder, inputs[0], inputs[1], inputs[2], inputs[3]);
+        let data = builder.build::<C>();
+        Self { data, inputs, outputs }
+    }
+
+    fn merge(&self, row: [u64; 4]) -> [u64; 4] {
+        let mut pw = PartialWitness::<F>::new();
+        for (&target, tile) in self.inputs.iter().zip(row) {
+            pw.set_target(target, F::from_canonical_u64(tile)).unwrap();
+        }
+        let witness = generate_partial_witness(pw, &self.data.prover_only, &self.data.common).unwrap();
+        self.outputs.map(|target| witness.get_target(target).to_canonical_u64())
+    }
+}
+
+/// The full move circuit, built once and shared by the board properties
+struct MoveCircuit {
+    data: CircuitData<F, C, D>,
+    targets: Game2048Targets,
+}
+
+impl MoveCircuit {
+    fn get() -> &'static Self {
+        static CIRCUIT: OnceLock<MoveCircuit> = OnceLock::new();
+        CIRCUIT.get_or_init(|| Self::new(Game2048Circuit::build_circuit()))
+    }
+
+    fn get_lookup() -> &'static Self {
+        static CIRCUIT: OnceLock<MoveCircuit> = OnceLock::new();
+        CIRCUIT.get_or_init(|| Self::new(Game2048Circuit::build_lookup_circuit()))
+    }
+
+    fn new((builder, targets): (CircuitBuilder<F, D>, Game2048Targets)) -> Self {
+        let data = builder.build::<C>();
+        Self { data, targets }
+    }
+
+    /// Prove and verify a move, so every gate constraint is checked and not only the wiring
+    fn accepts(&self, before: &Board, after: &Board, direction: Direction) -> bool {
+        let mut pw = PartialWitness::<F>::new();
+        self.targets.set_witness(&mut pw, before, after, direction).unwrap();
+        self.data.prove(pw).and_then(|proof| self.data.verify(proof)).is_ok()
+    }
+}
+
+fn all_rows() -> impl Iterator<Item = [u64; 4]> {
+    (0..ALPHABET.len().pow(4)).map(|mut index| {
+        let mut row = [0; 4];
+        for tile in row.iter_mut() {
+            *tile = ALPHABET[index % ALPHABET.len()];
+            index /= ALPHABET.len();
+        }
+        row
+    })
+}
+
+fn tile() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        3 => Just(0u64),
+        5 => (1u32..=11).prop_map(|exponent| 1u64 << exponent),
+    ]
+}
+
+fn board() -> impl Strategy<Value = Board> {
+    prop::array::uniform16(tile())
+}
+
+fn direction() -> impl Strategy<Value = Direction> {
+    prop::sample::select(Direction::ALL.to_vec())
+}
+
+#[test]
+fn merge_row_matches_native_for_every_row() {
+    let circuit = RowCircuit::build();
+    for row in all_rows() {
+        assert_eq!(circuit.merge(row), Game2048Engine::merge_row(row), "row {row:?}");
+    }
+}
+
+#[test]
+fn native_merge_known_rows() {
+    assert_eq!(Game2048Engine::merge_row([2, 2, 2, 2]), [4, 4, 0, 0]);
+    assert_eq!(Game2048Engine::merge_row([2, 2, 4, 0]), [4, 4, 0, 0]);
+    assert_eq!(Game2048Engine::merge_row([0, 4, 4, 8]), [8, 8, 0, 0]);
+    assert_eq!(Game2048Engine::merge_row([2, 0, 0, 2]), [4, 0, 0, 0]);
+    assert_eq!(Game2048Engine::merge_row([4, 2, 2, 4]), [4, 4, 4, 0]);
+    assert_eq!(Game2048Engine::merge_row([8, 4, 2, 0]), [8, 4, 2, 0]);
+}
+
+proptest! {
+    // Each case proves and verifies a move, so fewer cases than the native properties
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn move_circuit_accepts_native_moves(before in board(), direction in direction()) {
+        let after = Game2048Engine::apply_move(&before, direction);
+        prop_assert!(MoveCircuit::get().accepts(&before, &after, direction));
+    }
+}
+
+proptest! {
+    // The lookup circuit carries the whole merge table, so its proofs take seconds each
+    #![proptest_config(ProptestConfig::with_cases(4))]
+
+    #[test]
+    fn lookup_circuit_accepts_native_moves(before in board(), direction in direction()) {
+        let after = Game2048Engine::apply_move(&before, direction);
+        prop_assert!(MoveCircuit::get_lookup().accepts(&before, &after, direction));
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn native_moves_are_idempotent_without_merges(before in board(), direction in direction()) {
+        let after = Game2048Engine::apply_move(&before, direction);
+        let tiles = |board: &Board| board.iter().filter(|&&tile| tile != 0).count();
+        let sum = |board: &Board| board.iter().sum::<u64>();
+        prop_assert_eq!(sum(&before), sum(&after));
+        if tiles(&before) == tiles(&after) {
+            prop_assert_eq!(Game2048Engine::apply_move(&after, direction), after);
+        }
+    }
+}
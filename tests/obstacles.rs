@@ -0,0 +1,129 @@
+use game2048_plonky2::game2048::{ObstacleMask, ObstacleProver, C, F};
+use game2048_plonky2::{Board, CircuitProfile, Direction, Game2048Circuit, Game2048Engine, Game2048Error};
+use plonky2::field::types::{Field, PrimeField64};
+use plonky2::iop::witness::PartialWitness;
+
+/// Tiles of a row, before the walls of a pattern clear their cells
+const ROW: [u64; 4] = [2, 2, 4, 4];
+
+fn walls(pattern: usize) -> [bool; 4] {
+    [0, 1, 2, 3].map(|cell| pattern >> cell & 1 == 1)
+}
+
+/// Four rows with wall patterns first_pattern.. and ROW in their open cells
+fn board_with_patterns(first_pattern: usize) -> (Board, ObstacleMask) {
+    let mut board = [0; 16];
+    let mut obstacles = [false; 16];
+    for row in 0..4 {
+        for (col, wall) in walls(first_pattern + row).into_iter().enumerate() {
+            obstacles[row * 4 + col] = wall;
+            board[row * 4 + col] = if wall { 0 } else { ROW[col] };
+        }
+    }
+    (board, obstacles)
+}
+
+#[test]
+fn walls_split_rows_into_runs() {
+    for (walls, merged) in [
+        ([false, false, false, false], [4, 8, 0, 0]),
+        ([false, true, false, false], [2, 0, 8, 0]),
+        ([false, false, true, false], [4, 0, 0, 4]),
+        ([true, false, false, true], [0, 2, 4, 0]),
+        ([false, false, false, true], [4, 4, 0, 0]),
+        ([true, true, true, true], [0, 0, 0, 0]),
+    ] {
+        let row: [u64; 4] = std::array::from_fn(|cell| if walls[cell] { 0 } else { ROW[cell] });
+        assert_eq!(Game2048Engine::merge_row_with_walls(row, walls), merged, "{walls:?}");
+    }
+
+    let (board, obstacles) = board_with_patterns(0);
+    let mut on_wall = board;
+    on_wall[4] = 2;
+    let after_board = Game2048Engine::apply_move_with_obstacles(&board, &obstacles, Direction::Left);
+    assert!(matches!(
+        Game2048Engine::check_move_with_obstacles(&on_wall, &after_board, &obstacles, Direction::Left),
+        Err(Game2048Error::TileOnObstacle { cell: 4 })
+    ));
+    Game2048Engine::check_move_with_obstacles(&board, &after_board, &obstacles, Direction::Left).unwrap();
+}
+
+#[test]
+fn every_wall_pattern_in_a_row_is_proven() {
+    let (builder, targets) = Game2048Circuit::build_obstacle_circuit(CircuitProfile::Recursion);
+    let data = builder.build::<C>();
+
+    for first_pattern in [0, 4, 8, 12] {
+        let (board, obstacles) = board_with_patterns(first_pattern);
+        for direction in [Direction::Left, Direction::Right] {
+            let after_board = Game2048Engine::apply_move_with_obstacles(&board, &obstacles, direction);
+            Game2048Engine::check_move_with_obstacles(&board, &after_board, &obstacles, direction).unwrap();
+
+            let mut pw = PartialWitness::<F>::new();
+            targets.set_witness(&mut pw, &board, &after_board, &obstacles, direction).unwrap();
+            let proof = data.prove(pw).unwrap();
+            data.verify(proof.clone()).unwrap();
+
+            let public_mask: Vec<bool> = proof.public_inputs[33..49].iter().map(|input| input.is_one()).collect();
+            assert_eq!(public_mask, obstacles);
+            let public_board: Vec<u64> =
+                proof.public_inputs[16..32].iter().map(|input| input.to_canonical_u64()).collect();
+            assert_eq!(public_board, after_board);
+        }
+    }
+}
+
+#[test]
+fn tiles_cannot_cross_or_sit_on_walls() {
+    let (builder, targets) = Game2048Circuit::build_obstacle_circuit(CircuitProfile::Recursion);
+    let data = builder.build::<C>();
+    let (board, obstacles) = board_with_patterns(0);
+
+    // Row 2 has a wall in cell 1: sliding over it as on an open row fails to prove
+    let mut crossed = Game2048Engine::apply_move_with_obstacles(&board, &obstacles, Direction::Left);
+    crossed[8..12].copy_from_slice(&Game2048Engine::merge_row([2, 0, 4, 4]));
+
+    // A tile on the wall of row 1 that stays put
+    let mut on_wall = board;
+    on_wall[4] = 8;
+    let mut on_wall_after = Game2048Engine::apply_move_with_obstacles(&board, &obstacles, Direction::Left);
+    on_wall_after[4] = 8;
+
+    // Without the wall, crossing it would be legal
+    let mut no_wall = obstacles;
+    no_wall[9] = false;
+    assert_eq!(Game2048Engine::apply_move_with_obstacles(&board, &no_wall, Direction::Left), crossed);
+
+    for (before_board, after_board) in [(board, crossed), (on_wall, on_wall_after)] {
+        let mut pw = PartialWitness::<F>::new();
+        targets.set_witness(&mut pw, &before_board, &after_board, &obstacles, Direction::Left).unwrap();
+        assert!(data.prove(pw).is_err(), "{after_board:?} accepted");
+    }
+}
+
+#[test]
+fn obstacle_prover_checks_moves_before_proving() {
+    let prover = ObstacleProver::new(CircuitProfile::Recursion);
+    let (board, obstacles) = board_with_patterns(4);
+    let after_board = Game2048Engine::apply_move_with_obstacles(&board, &obstacles, Direction::Right);
+    let proof = prover.prove(&board, &after_board, &obstacles, Direction::Right).unwrap();
+    prover.verify(&proof).unwrap();
+    assert_eq!(proof.before_board(), board);
+    assert_eq!(proof.after_board(), after_board);
+    assert_eq!(proof.direction(), Some(Direction::Right));
+    assert_eq!(proof.obstacles(), obstacles);
+
+    // The move as on an open board, and a tile on a wall, are reported natively
+    let open_board = Game2048Engine::apply_move(&board, Direction::Right);
+    assert!(matches!(
+        prover.prove(&board, &open_board, &obstacles, Direction::Right),
+        Err(Game2048Error::IllegalMove { direction: Direction::Right, .. })
+    ));
+    let wall = obstacles.iter().position(|&wall| wall).unwrap();
+    let mut on_wall = board;
+    on_wall[wall] = 2;
+    assert!(matches!(
+        prover.prove(&on_wall, &after_board, &obstacles, Direction::Right),
+        Err(Game2048Error::TileOnObstacle { cell }) if cell == wall
+    ));
+}
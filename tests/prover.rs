@@ -0,0 +1,160 @@
+use game2048_plonky2::game2048::{F, IDENTITY_LEN};
+use game2048_plonky2::{
+    CellMismatch, CircuitProfile, Direction, Game2048Circuit, Game2048Engine, Game2048Error, Game2048Prover,
+    Game2048Verifier, Line, PlayerIdentity,
+};
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::HashOut;
+use plonky2::plonk::config::{KeccakGoldilocksConfig, PoseidonGoldilocksConfig};
+
+const BEFORE_BOARD: [u64; 16] = [
+    2, 2, 4, 8, //
+    2, 0, 4, 4, //
+    2, 2, 2, 4, //
+    0, 2, 4, 4, //
+];
+
+fn identity() -> PlayerIdentity {
+    PlayerIdentity::new(&[F::from_canonical_u64(42)], 1)
+}
+
+#[test]
+fn prove_and_verify_a_move() {
+    let prover = Game2048Prover::new();
+    let after_board = Game2048Engine::apply_move(&BEFORE_BOARD, Direction::Right);
+
+    let proof = prover.prove(&BEFORE_BOARD, &after_board, Direction::Right, &identity()).unwrap();
+    prover.verifier().verify(&proof).unwrap();
+
+    assert_eq!(proof.before_board(), BEFORE_BOARD);
+    assert_eq!(proof.after_board(), after_board);
+    assert_eq!(proof.direction(), Some(Direction::Right));
+    proof.check_player_identity(&identity()).unwrap();
+}
+
+#[test]
+fn move_proof_is_bound_to_the_player_identity() {
+    let prover = Game2048Prover::new();
+    let after_board = Game2048Engine::apply_move(&BEFORE_BOARD, Direction::Left);
+    let proof = prover.prove(&BEFORE_BOARD, &after_board, Direction::Left, &identity()).unwrap();
+    assert_eq!(proof.player_identity(), identity());
+
+    // Claiming the proof for another session fails
+    let other_session = PlayerIdentity::new(&[F::from_canonical_u64(42)], 2);
+    assert!(matches!(proof.check_player_identity(&other_session), Err(Game2048Error::IdentityMismatch)));
+    let mut claimed = proof.clone();
+    claimed.proof.public_inputs[33..33 + IDENTITY_LEN].copy_from_slice(&other_session.elements());
+    assert!(prover.verifier().verify(&claimed).is_err());
+}
+
+#[test]
+fn keccak_and_poseidon_prove_the_same_move() {
+    let after_board = Game2048Engine::apply_move(&BEFORE_BOARD, Direction::Left);
+
+    let poseidon = Game2048Prover::new();
+    let poseidon_proof = poseidon.prove(&BEFORE_BOARD, &after_board, Direction::Left, &identity()).unwrap();
+    poseidon.verifier().verify(&poseidon_proof).unwrap();
+
+    let keccak = Game2048Prover::<KeccakGoldilocksConfig>::with_config();
+    let keccak_proof = keccak.prove(&BEFORE_BOARD, &after_board, Direction::Left, &identity()).unwrap();
+    let verifier = keccak.verifier();
+    verifier.verify(&keccak_proof).unwrap();
+    verifier.check_circuit_digest(&verifier.circuit_digest()).unwrap();
+
+    assert_eq!(keccak_proof.proof.public_inputs, poseidon_proof.proof.public_inputs);
+    let keccak_proof = verifier.proof_from_bytes(keccak_proof.to_bytes()).unwrap();
+    assert_eq!(keccak_proof.after_board(), after_board);
+}
+
+#[test]
+fn every_profile_proves_and_verifies() {
+    let after_board = Game2048Engine::apply_move(&BEFORE_BOARD, Direction::Up);
+    let mut proof_sizes = Vec::new();
+    for profile in CircuitProfile::ALL {
+        let prover = Game2048Prover::<PoseidonGoldilocksConfig>::with_profile(profile);
+        let proof = prover.prove(&BEFORE_BOARD, &after_board, Direction::Up, &identity()).unwrap();
+        prover.verifier().verify(&proof).unwrap();
+        proof_sizes.push(proof.to_bytes().len());
+    }
+
+    let [fast_prove, small_proof, recursion, _] = proof_sizes[..] else { unreachable!() };
+    assert!(fast_prove > recursion && recursion > small_proof);
+}
+
+#[test]
+fn illegal_move_is_reported_before_proving() {
+    let prover = Game2048Prover::new();
+    let after_board = Game2048Engine::apply_move(&BEFORE_BOARD, Direction::Left);
+
+    let result = prover.prove(&BEFORE_BOARD, &after_board, Direction::Right, &identity());
+    assert!(matches!(result, Err(Game2048Error::IllegalMove { direction: Direction::Right, .. })));
+}
+
+#[test]
+fn illegal_move_reports_each_mismatched_cell() {
+    let mut after_board = Game2048Engine::apply_move(&BEFORE_BOARD, Direction::Down);
+    after_board[9] = 16;
+
+    let mismatches = Game2048Engine::diagnose_move(&BEFORE_BOARD, &after_board, Direction::Down);
+    assert_eq!(
+        mismatches,
+        vec![CellMismatch { line: Line::Column(1), row: 2, col: 1, expected: 2, actual: 16 }]
+    );
+
+    let err = Game2048Engine::check_move(&BEFORE_BOARD, &after_board, Direction::Down).unwrap_err();
+    assert_eq!(err.to_string(), "illegal move Down; column 1, cell (2, 1): expected 2, found 16");
+}
+
+#[test]
+fn invalid_tiles_and_shapes_are_rejected() {
+    let mut board = BEFORE_BOARD;
+    board[5] = 6;
+    assert!(matches!(
+        Game2048Engine::validate_board(&board),
+        Err(Game2048Error::InvalidTile { cell: 5, value: 6 })
+    ));
+    board[5] = 1;
+    assert!(matches!(
+        Game2048Engine::parse_board(&board),
+        Err(Game2048Error::InvalidTile { cell: 5, value: 1 })
+    ));
+    assert!(matches!(
+        Game2048Engine::parse_board(&BEFORE_BOARD[..15]),
+        Err(Game2048Error::InvalidBoardShape { len: 15 })
+    ));
+    assert_eq!(Game2048Engine::parse_board(&BEFORE_BOARD).unwrap(), BEFORE_BOARD);
+}
+
+#[test]
+fn proofs_and_verifiers_round_trip_through_bytes() {
+    let prover = Game2048Prover::new();
+    let after_board = Game2048Engine::apply_move(&BEFORE_BOARD, Direction::Up);
+    let proof = prover.prove(&BEFORE_BOARD, &after_board, Direction::Up, &identity()).unwrap();
+
+    let verifier: Game2048Verifier = Game2048Verifier::from_bytes(prover.verifier().to_bytes().unwrap()).unwrap();
+    verifier.check_circuit_digest(&prover.verifier().circuit_digest()).unwrap();
+    let proof = verifier.proof_from_bytes(proof.to_bytes()).unwrap();
+    verifier.verify(&proof).unwrap();
+
+    assert!(matches!(
+        verifier.proof_from_bytes(vec![1, 2, 3]),
+        Err(Game2048Error::DeserializationFailure(_))
+    ));
+    assert!(matches!(
+        Game2048Verifier::<PoseidonGoldilocksConfig>::from_bytes(vec![1, 2, 3]),
+        Err(Game2048Error::DeserializationFailure(_))
+    ));
+}
+
+#[test]
+fn digest_of_another_circuit_is_a_mismatch() {
+    let verifier = Game2048Prover::new().verifier();
+    let (builder, _) = Game2048Circuit::build_lookup_circuit();
+    let other_digest = builder.build::<game2048_plonky2::game2048::C>().verifier_only.circuit_digest;
+
+    assert!(matches!(
+        verifier.check_circuit_digest(&other_digest),
+        Err(Game2048Error::CircuitDigestMismatch { .. })
+    ));
+    assert!(verifier.check_circuit_digest(&HashOut::ZERO).is_err());
+}
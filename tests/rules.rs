@@ -0,0 +1,348 @@
+use game2048_plonky2::game2048::{CubeDirection, CubeProver, ObstacleProver, C, D, F};
+use game2048_plonky2::game2048::{MAX_FIBONACCI_TILE, MAX_THREES_TILE, MAX_TILE_EXPONENT};
+use game2048_plonky2::{
+    CircuitProfile, ClassicRules, Direction, FibonacciRules, Game2048Circuit, Game2048Engine, Game2048Error,
+    Game2048GameProver, Game2048Prover, Game2048TraceProver, PlayerEntropy, PlayerIdentity, RuleSet, ServerSeed,
+    ThreesRules, TimedMove,
+};
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::HashOut;
+use plonky2::iop::target::{BoolTarget, Target};
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+
+fn identity() -> PlayerIdentity {
+    PlayerIdentity::new(&[F::from_canonical_u64(42)], 1)
+}
+
+/// A variant on powers of three, where two equal tiles merge into one three times as large
+struct PowersOfThree;
+
+impl RuleSet for PowersOfThree {
+    fn name(&self) -> &'static str {
+        "powers-of-three"
+    }
+
+    fn is_valid_tile(&self, value: u64) -> bool {
+        let mut value = value;
+        while value > 3 && value.is_multiple_of(3) {
+            value /= 3;
+        }
+        value == 0 || value == 3
+    }
+
+    fn can_merge(&self, a: u64, b: u64) -> bool {
+        a == b
+    }
+
+    fn merged_value(&self, a: u64, _b: u64) -> u64 {
+        a * 3
+    }
+
+    fn spawn_value(&self, _random: u64) -> u64 {
+        3
+    }
+
+    fn add_can_merge(&self, builder: &mut CircuitBuilder<F, D>, a: Target, b: Target) -> BoolTarget {
+        ClassicRules.add_can_merge(builder, a, b)
+    }
+
+    fn add_merged_value(&self, builder: &mut CircuitBuilder<F, D>, a: Target, _b: Target) -> Target {
+        builder.mul_const(F::from_canonical_u64(3), a)
+    }
+
+    fn add_spawn_value(&self, builder: &mut CircuitBuilder<F, D>, _random: Target) -> Target {
+        builder.constant(F::from_canonical_u64(3))
+    }
+}
+
+const BEFORE_BOARD: [u64; 16] = [
+    3, 3, 9, 27, //
+    3, 0, 9, 9, //
+    3, 3, 3, 9, //
+    0, 3, 9, 9, //
+];
+
+#[test]
+fn classic_rules_match_the_engine_and_score_merged_tiles() {
+    let board = [
+        2, 2, 4, 8, //
+        2, 0, 4, 4, //
+        2, 2, 2, 4, //
+        0, 2, 4, 4, //
+    ];
+    for direction in Direction::ALL {
+        assert_eq!(ClassicRules.apply_move(&board, direction), Game2048Engine::apply_move(&board, direction));
+    }
+
+    assert!(ClassicRules.is_valid_tile(1 << MAX_TILE_EXPONENT));
+    for tile in [1, 3, 6, 1 << (MAX_TILE_EXPONENT + 1)] {
+        assert!(!ClassicRules.is_valid_tile(tile), "{tile} accepted");
+    }
+
+    // Left merges one pair per row, scoring the merged tile
+    let (after_board, score) = ClassicRules.apply_move_scored(&board, Direction::Left);
+    assert_eq!(after_board[..4], [4, 4, 8, 0]);
+    assert_eq!(score, 4 + 8 + 4 + 8);
+}
+
+#[test]
+fn a_custom_rule_set_proves_its_own_moves() {
+    let after_board = PowersOfThree.apply_move(&BEFORE_BOARD, Direction::Left);
+    assert_eq!(after_board[..8], [9, 9, 27, 0, 3, 27, 0, 0]);
+    assert_eq!(PowersOfThree.apply_move_scored(&BEFORE_BOARD, Direction::Left).1, 9 + 27 + 9 + 27);
+
+    let prover = Game2048Prover::<PoseidonGoldilocksConfig>::with_rules(CircuitProfile::Recursion, PowersOfThree);
+    assert_eq!(prover.rules().name(), "powers-of-three");
+    let proof = prover.prove(&BEFORE_BOARD, &after_board, Direction::Left, &identity()).unwrap();
+    prover.verifier().verify(&proof).unwrap();
+
+    // The classic rules reject the variant's tiles, and the variant rejects a classic merge
+    let classic = Game2048Prover::<PoseidonGoldilocksConfig>::with_profile(CircuitProfile::Recursion);
+    assert!(matches!(
+        classic.prove(&BEFORE_BOARD, &after_board, Direction::Left, &identity()),
+        Err(Game2048Error::InvalidTile { cell: 0, value: 3 })
+    ));
+    let doubled = [6, 9, 27, 0, 3, 27, 0, 0, 6, 3, 9, 0, 3, 27, 0, 0];
+    assert!(matches!(
+        prover.prove(&BEFORE_BOARD, &doubled, Direction::Left, &identity()),
+        Err(Game2048Error::InvalidTile { cell: 0, value: 6 })
+    ));
+}
+
+#[test]
+fn the_circuit_enforces_the_custom_merged_value() {
+    let (builder, targets) = Game2048Circuit::build_circuit_with_rules(CircuitProfile::Recursion, &PowersOfThree);
+    let data = builder.build::<C>();
+
+    // Bypass the native check: doubling instead of tripling fails to prove
+    let mut doubled = PowersOfThree.apply_move(&BEFORE_BOARD, Direction::Left);
+    doubled[0] = 6;
+    let mut pw = PartialWitness::<F>::new();
+    targets.set_witness(&mut pw, &BEFORE_BOARD, &doubled, Direction::Left).unwrap();
+    assert!(data.prove(pw).is_err());
+
+    let mut pw = PartialWitness::<F>::new();
+    let after_board = PowersOfThree.apply_move(&BEFORE_BOARD, Direction::Left);
+    targets.set_witness(&mut pw, &BEFORE_BOARD, &after_board, Direction::Left).unwrap();
+    data.verify(data.prove(pw).unwrap()).unwrap();
+}
+
+const FIBONACCI_BOARD: [u64; 16] = [
+    1, 1, 2, 3, //
+    2, 3, 5, 0, //
+    1, 3, 0, 8, //
+    5, 8, 13, 21, //
+];
+
+#[test]
+fn consecutive_fibonacci_numbers_merge() {
+    for tile in [0, 1, 2, 3, 5, 8, 13, MAX_FIBONACCI_TILE] {
+        assert!(FibonacciRules.is_valid_tile(tile), "{tile} rejected");
+    }
+    for tile in [4, 6, 7, 9, 1 << 30] {
+        assert!(!FibonacciRules.is_valid_tile(tile), "{tile} accepted");
+    }
+    for (a, b) in [(1, 1), (1, 2), (2, 1), (3, 5), (21, 13)] {
+        assert!(FibonacciRules.can_merge(a, b), "{a}+{b} rejected");
+    }
+    for (a, b) in [(0, 1), (1, 0), (1, 3), (2, 2), (5, 5), (8, 21)] {
+        assert!(!FibonacciRules.can_merge(a, b), "{a}+{b} accepted");
+    }
+
+    let (after_board, score) = FibonacciRules.apply_move_scored(&FIBONACCI_BOARD, Direction::Left);
+    assert_eq!(after_board, [2, 5, 0, 0, 5, 5, 0, 0, 1, 3, 8, 0, 13, 34, 0, 0]);
+    assert_eq!(score, 2 + 5 + 5 + 13 + 34);
+
+    for spawn_index in 0..32 {
+        let (_, value) = FibonacciRules.spawn_position(&FIBONACCI_BOARD, &HashOut::ZERO, spawn_index, None).unwrap();
+        assert!(value == 1 || value == 2);
+    }
+}
+
+#[test]
+fn fibonacci_moves_are_proven_and_forgeries_rejected() {
+    let prover = Game2048Prover::<PoseidonGoldilocksConfig>::with_rules(CircuitProfile::Recursion, FibonacciRules);
+    for direction in Direction::ALL {
+        let after_board = FibonacciRules.apply_move(&FIBONACCI_BOARD, direction);
+        let proof = prover.prove(&FIBONACCI_BOARD, &after_board, direction, &identity()).unwrap();
+        prover.verifier().verify(&proof).unwrap();
+    }
+
+    // Bypass the native check: merging 2+3 before 1+1, and a board holding a 4, fail to prove
+    let (builder, targets) = Game2048Circuit::build_circuit_with_rules(CircuitProfile::Recursion, &FibonacciRules);
+    let data = builder.build::<C>();
+    let mut wrong_pair = FibonacciRules.apply_move(&FIBONACCI_BOARD, Direction::Left);
+    wrong_pair[..4].copy_from_slice(&[1, 1, 5, 0]);
+    let mut not_fibonacci = FIBONACCI_BOARD;
+    not_fibonacci[..4].copy_from_slice(&[4, 4, 0, 0]);
+    for (before_board, after_board) in [
+        (FIBONACCI_BOARD, wrong_pair),
+        (not_fibonacci, FibonacciRules.apply_move(&not_fibonacci, Direction::Left)),
+    ] {
+        let mut pw = PartialWitness::<F>::new();
+        targets.set_witness(&mut pw, &before_board, &after_board, Direction::Left).unwrap();
+        assert!(data.prove(pw).is_err(), "{before_board:?} -> {after_board:?} accepted");
+    }
+}
+
+const THREES_BOARD: [u64; 16] = [
+    0, 1, 2, 3, //
+    1, 2, 3, 3, //
+    1, 1, 2, 2, //
+    3, 6, 0, 6, //
+];
+
+#[test]
+fn threes_tiles_move_at_most_one_step() {
+    for (row, merged, score) in [
+        ([0, 1, 2, 3], [1, 2, 3, 0], 0),
+        ([0, 0, 0, 3], [0, 0, 3, 0], 0),
+        ([1, 2, 3, 3], [3, 3, 3, 0], 3),
+        ([3, 3, 3, 3], [6, 3, 3, 0], 6),
+        ([1, 1, 2, 2], [1, 3, 2, 0], 3),
+        ([2, 2, 1, 0], [2, 3, 0, 0], 3),
+        ([3, 6, 0, 6], [3, 6, 6, 0], 0),
+        ([3, 6, 12, 24], [3, 6, 12, 24], 0),
+    ] {
+        assert_eq!(ThreesRules.merge_row_scored(row), (merged, score), "{row:?}");
+    }
+    for tile in [0, 1, 2, 3, 6, 12, 3 << 20, MAX_THREES_TILE] {
+        assert!(ThreesRules.is_valid_tile(tile), "{tile} rejected");
+    }
+    for tile in [4, 5, 9, 18, MAX_THREES_TILE * 2] {
+        assert!(!ThreesRules.is_valid_tile(tile), "{tile} accepted");
+    }
+    for spawn_index in 0..32 {
+        let (_, value) = ThreesRules.spawn_position(&THREES_BOARD, &HashOut::ZERO, spawn_index, None).unwrap();
+        assert!((1..=3).contains(&value));
+    }
+}
+
+#[test]
+fn threes_tiles_spawn_on_the_edge_the_tiles_left() {
+    // The cells of the edge opposite each direction: right column after Left, and so on
+    for (direction, edge) in [
+        (Direction::Left, [3, 7, 11, 15]),
+        (Direction::Right, [0, 4, 8, 12]),
+        (Direction::Up, [12, 13, 14, 15]),
+        (Direction::Down, [0, 1, 2, 3]),
+    ] {
+        let allowed = ThreesRules.spawn_cells(Some(direction));
+        assert_eq!((0..16).filter(|&cell| allowed[cell]).collect::<Vec<_>>(), edge, "{direction:?}");
+
+        let moved_board = ThreesRules.apply_move(&THREES_BOARD, direction);
+        for spawn_index in 0..32 {
+            let seed = HashOut::from_partial(&[F::from_canonical_u64(spawn_index)]);
+            if let Some((cell, _)) = ThreesRules.spawn_position(&moved_board, &seed, spawn_index, Some(direction)) {
+                assert!(edge.contains(&cell) && moved_board[cell] == 0, "{direction:?}: cell {cell}");
+            }
+        }
+        assert_eq!(ClassicRules.spawn_cells(Some(direction)), [true; 16]);
+    }
+    assert_eq!(ThreesRules.spawn_cells(None), [true; 16]);
+
+    // A full edge gets no tile, even with empty cells elsewhere
+    let mut board = [0; 16];
+    for cell in [3, 7, 11, 15] {
+        board[cell] = 3;
+    }
+    assert_eq!(ThreesRules.spawn_position(&board, &HashOut::ZERO, 0, Some(Direction::Left)), None);
+}
+
+#[test]
+fn threes_moves_are_proven_and_full_slides_rejected() {
+    let prover = Game2048Prover::<PoseidonGoldilocksConfig>::with_rules(CircuitProfile::Recursion, ThreesRules);
+    for direction in Direction::ALL {
+        let after_board = ThreesRules.apply_move(&THREES_BOARD, direction);
+        let proof = prover.prove(&THREES_BOARD, &after_board, direction, &identity()).unwrap();
+        prover.verifier().verify(&proof).unwrap();
+    }
+
+    // Bypass the native check: sliding a tile all the way, combining two 1s, or
+    // combining two 5s, which are not Threes tiles, fails to prove
+    let (builder, targets) = Game2048Circuit::build_circuit_with_rules(CircuitProfile::Recursion, &ThreesRules);
+    let data = builder.build::<C>();
+    let after_board = ThreesRules.apply_move(&THREES_BOARD, Direction::Left);
+    let mut full_slide = after_board;
+    full_slide[12..].copy_from_slice(&[3, 12, 0, 0]);
+    let mut ones_combined = after_board;
+    ones_combined[8..12].copy_from_slice(&[2, 2, 2, 0]);
+    let mut fives = THREES_BOARD;
+    fives[..4].copy_from_slice(&[5, 5, 0, 0]);
+    for (before_board, forged) in [
+        (THREES_BOARD, full_slide),
+        (THREES_BOARD, ones_combined),
+        (fives, ThreesRules.apply_move(&fives, Direction::Left)),
+    ] {
+        let mut pw = PartialWitness::<F>::new();
+        targets.set_witness(&mut pw, &before_board, &forged, Direction::Left).unwrap();
+        assert!(data.prove(pw).is_err(), "{before_board:?} -> {forged:?} accepted");
+    }
+}
+
+#[test]
+fn variant_games_spawn_and_move_under_their_rules() {
+    let (server_seed, player_entropy): (ServerSeed, PlayerEntropy) =
+        (ServerSeed([1, 2, 3, 4].map(F::from_canonical_u64)), [11, 12, 13, 14].map(F::from_canonical_u64));
+    let seed = Game2048Engine::combine_seed(&server_seed, &player_entropy, &identity());
+    let directions = [Direction::Left, Direction::Up, Direction::Right];
+    let moves: Vec<_> = directions
+        .iter()
+        .zip([1_000, 1_100, 1_200])
+        .map(|(&direction, timestamp)| TimedMove { direction, timestamp })
+        .collect();
+
+    let boards = FibonacciRules.play(&seed, &directions);
+    assert!(boards.iter().flatten().all(|&tile| FibonacciRules.is_valid_tile(tile)));
+    assert_ne!(boards, Game2048Engine::play(&seed, &directions));
+
+    let trace_prover = Game2048TraceProver::with_rules(3, 100, CircuitProfile::Recursion, FibonacciRules).unwrap();
+    let proof = trace_prover.prove(&server_seed, &player_entropy, &identity(), &moves).unwrap();
+    trace_prover.verify(&proof).unwrap();
+    assert_eq!(proof.final_board(), boards[3]);
+
+    let game_prover = Game2048GameProver::with_rules(3, 100, CircuitProfile::Recursion, FibonacciRules).unwrap();
+    let proof = game_prover.prove(&server_seed, &player_entropy, &identity(), &moves).unwrap();
+    game_prover.verify(&proof).unwrap();
+    assert_eq!(proof.final_board(), boards[3]);
+
+    // The Threes trace circuit spawns on the edge each move left behind, as natively
+    let boards = ThreesRules.play(&seed, &directions);
+    let trace_prover = Game2048TraceProver::with_rules(3, 100, CircuitProfile::Recursion, ThreesRules).unwrap();
+    let proof = trace_prover.prove(&server_seed, &player_entropy, &identity(), &moves).unwrap();
+    trace_prover.verify(&proof).unwrap();
+    assert_eq!(proof.final_board(), boards[3]);
+}
+
+#[test]
+fn obstacle_and_cube_moves_follow_the_rules() {
+    let mut obstacles = [false; 16];
+    obstacles[7] = true;
+    let after_board = FibonacciRules.apply_move_with_obstacles(&FIBONACCI_BOARD, &obstacles, Direction::Left);
+    assert_eq!(after_board[4..8], [5, 5, 0, 0]);
+
+    let prover = ObstacleProver::with_rules(CircuitProfile::Recursion, FibonacciRules);
+    let proof = prover.prove(&FIBONACCI_BOARD, &after_board, &obstacles, Direction::Left).unwrap();
+    prover.verify(&proof).unwrap();
+    let classic = ObstacleProver::new(CircuitProfile::Recursion);
+    assert!(matches!(
+        classic.prove(&FIBONACCI_BOARD, &after_board, &obstacles, Direction::Left),
+        Err(Game2048Error::InvalidTile { cell: 0, value: 1 })
+    ));
+
+    let mut cube = [0; 64];
+    cube[..16].copy_from_slice(&FIBONACCI_BOARD);
+    cube[16..20].copy_from_slice(&[1, 2, 0, 3]);
+    let after_cube = FibonacciRules.apply_cube_move(&cube, CubeDirection::Left);
+    assert_eq!(after_cube[16..20], [3, 3, 0, 0]);
+
+    let prover = CubeProver::with_rules(CircuitProfile::Recursion, FibonacciRules);
+    let proof = prover.prove(&cube, &after_cube, CubeDirection::Left).unwrap();
+    prover.verify(&proof).unwrap();
+    assert!(matches!(
+        CubeProver::new(CircuitProfile::Recursion).prove(&cube, &after_cube, CubeDirection::Left),
+        Err(Game2048Error::InvalidTile { cell: 0, value: 1 })
+    ));
+}
@@ -0,0 +1,64 @@
+use game2048_plonky2::game2048::{SeedTargets, C, D, F};
+use game2048_plonky2::{Game2048Circuit, Game2048Engine, Game2048Error, PlayerEntropy, PlayerIdentity, ServerSeed};
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::HashOut;
+use plonky2::iop::witness::PartialWitness;
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+
+fn identity() -> PlayerIdentity {
+    PlayerIdentity::new(&[F::from_canonical_u64(42)], 1)
+}
+
+/// Circuit exposing the server commitment, the player entropy and the combined seed
+fn seed_circuit() -> (CircuitData<F, C, D>, SeedTargets) {
+    let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+    let targets = Game2048Circuit::add_seed(&mut builder);
+    builder.register_public_inputs(&targets.server_commitment.elements);
+    builder.register_public_inputs(&targets.player_entropy);
+    builder.register_public_inputs(&targets.seed.elements);
+    (builder.build::<C>(), targets)
+}
+
+#[test]
+fn revealed_seed_must_match_its_commitment() {
+    let server_seed = ServerSeed::random();
+    let commitment = server_seed.commitment();
+    Game2048Engine::check_seed_reveal(&commitment, &server_seed).unwrap();
+
+    let other_seed = ServerSeed([server_seed.0[0] + F::ONE, server_seed.0[1], server_seed.0[2], server_seed.0[3]]);
+    assert!(matches!(
+        Game2048Engine::check_seed_reveal(&commitment, &other_seed),
+        Err(Game2048Error::SeedCommitmentMismatch)
+    ));
+}
+
+#[test]
+fn circuit_derives_the_native_seed() {
+    let (data, targets) = seed_circuit();
+    let server_seed = ServerSeed::random();
+    let player_entropy: PlayerEntropy = [1, 2, 3, 4].map(F::from_canonical_u64);
+
+    let mut pw = PartialWitness::<F>::new();
+    targets.set_witness(&mut pw, &server_seed, &player_entropy, &identity()).unwrap();
+    let proof = data.prove(pw).unwrap();
+    data.verify(proof.clone()).unwrap();
+
+    let public_inputs = &proof.public_inputs;
+    assert_eq!(HashOut::from_partial(&public_inputs[0..4]), server_seed.commitment());
+    assert_eq!(public_inputs[4..8], player_entropy);
+    let seed = Game2048Engine::combine_seed(&server_seed, &player_entropy, &identity());
+    assert_eq!(HashOut::from_partial(&public_inputs[8..12]), seed);
+}
+
+#[test]
+fn every_input_changes_the_seed() {
+    let server_seed = ServerSeed::random();
+    let player_entropy = [F::ZERO; 4];
+    let seed = Game2048Engine::combine_seed(&server_seed, &player_entropy, &identity());
+
+    assert_ne!(seed, Game2048Engine::combine_seed(&ServerSeed::random(), &player_entropy, &identity()));
+    assert_ne!(seed, Game2048Engine::combine_seed(&server_seed, &[F::ONE, F::ZERO, F::ZERO, F::ZERO], &identity()));
+    let other_session = PlayerIdentity::new(&[F::from_canonical_u64(42)], 2);
+    assert_ne!(seed, Game2048Engine::combine_seed(&server_seed, &player_entropy, &other_session));
+}
@@ -0,0 +1,241 @@
+use game2048_plonky2::game2048::{
+    Board, Direction, Game2048Circuit, Game2048Engine, Game2048Targets, C, D, F, MAX_LOOKUP_EXPONENT, MAX_TILE_EXPONENT,
+};
+use plonky2::field::types::{Field, Field64, PrimeField64};
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitData;
+
+/// Public inputs of one move, with the direction as a raw field value so it can go out of range
+#[derive(Clone, Debug)]
+struct MoveWitness {
+    before: Board,
+    after: Board,
+    direction: F,
+}
+
+impl MoveWitness {
+    fn valid(before: Board, direction: Direction) -> Self {
+        let after = Game2048Engine::apply_move(&before, direction);
+        Self { before, after, direction: F::from_canonical_u64(direction.as_u64()) }
+    }
+
+    /// Whether the native rules accept this witness, used to drop mutations that happen to be legal
+    fn is_legal(&self) -> bool {
+        Direction::ALL
+            .iter()
+            .any(|&direction| F::from_canonical_u64(direction.as_u64()) == self.direction
+                && Game2048Engine::apply_move(&self.before, direction) == self.after)
+    }
+
+    fn with_after(&self, after: Board) -> Self {
+        Self { after, ..self.clone() }
+    }
+}
+
+/// Every mutation of a valid witness, labelled for failure messages
+fn mutations(valid: &MoveWitness, direction: Direction) -> Vec<(String, MoveWitness)> {
+    let mut mutations = Vec::new();
+
+    // Single-cell changes
+    for cell in 0..16 {
+        let tile = valid.after[cell];
+        let replacements = if tile == 0 { vec![2, 4] } else { vec![0, tile * 2, tile + 1] };
+        for replacement in replacements {
+            let mut after = valid.after;
+            after[cell] = replacement;
+            mutations.push((format!("cell {cell} set to {replacement}"), valid.with_after(after)));
+        }
+    }
+
+    // Swapped rows
+    for i in 0..4 {
+        for j in i + 1..4 {
+            let mut after = valid.after;
+            for col in 0..4 {
+                after.swap(i * 4 + col, j * 4 + col);
+            }
+            mutations.push((format!("rows {i} and {j} swapped"), valid.with_after(after)));
+        }
+    }
+
+    // Transposed board
+    let mut transposed = valid.after;
+    for row in 0..4 {
+        for col in 0..4 {
+            transposed[col * 4 + row] = valid.after[row * 4 + col];
+        }
+    }
+    mutations.push(("after_board transposed".to_string(), valid.with_after(transposed)));
+
+    // Lines merged a second time, e.g. [2,2,4,0] -> [8,0,0,0] instead of [4,4,0,0]
+    for (index, line) in Game2048Engine::lines(direction).iter().enumerate() {
+        let merged_twice = Game2048Engine::merge_row(line.map(|cell| valid.after[cell]));
+        let mut after = valid.after;
+        for (&cell, tile) in line.iter().zip(merged_twice) {
+            after[cell] = tile;
+        }
+        mutations.push((format!("line {index} merged twice"), valid.with_after(after)));
+    }
+
+    // Wrong direction
+    for other in Direction::ALL {
+        let witness = MoveWitness { direction: F::from_canonical_u64(other.as_u64()), ..valid.clone() };
+        mutations.push((format!("direction {other:?}"), witness));
+    }
+    for direction in [F::from_canonical_u64(4), F::from_canonical_u64(5), F::NEG_ONE] {
+        let witness = MoveWitness { direction, ..valid.clone() };
+        mutations.push((format!("direction {direction} out of range"), witness));
+    }
+
+    mutations.retain(|(_, witness)| !witness.is_legal());
+    mutations
+}
+
+struct Harness {
+    data: CircuitData<F, C, D>,
+    targets: Game2048Targets,
+}
+
+impl Harness {
+    fn new() -> Self {
+        Self::from_builder(Game2048Circuit::build_circuit())
+    }
+
+    fn lookup() -> Self {
+        Self::from_builder(Game2048Circuit::build_lookup_circuit())
+    }
+
+    fn from_builder((builder, targets): (CircuitBuilder<F, D>, Game2048Targets)) -> Self {
+        let data = builder.build::<C>();
+        Self { data, targets }
+    }
+
+    /// Assert that every mutation of every base witness fails to prove
+    fn assert_mutations_rejected(&self) {
+        let mut checked = 0;
+        for before in boards() {
+            for direction in Direction::ALL {
+                let valid = MoveWitness::valid(before, direction);
+                for (label, witness) in mutations(&valid, direction) {
+                    assert!(self.prove(&witness).is_err(), "{label} accepted for {valid:?}");
+                    checked += 1;
+                }
+            }
+        }
+        assert!(checked > 500, "only {checked} mutations checked");
+    }
+
+    fn prove(&self, witness: &MoveWitness) -> anyhow::Result<()> {
+        let mut pw = PartialWitness::<F>::new();
+        for (&target, &tile) in self.targets.before_board.iter().zip(&witness.before) {
+            pw.set_target(target, F::from_canonical_u64(tile))?;
+        }
+        for (&target, &tile) in self.targets.after_board.iter().zip(&witness.after) {
+            pw.set_target(target, F::from_canonical_u64(tile))?;
+        }
+        pw.set_target(self.targets.direction, witness.direction)?;
+        let proof = self.data.prove(pw)?;
+        self.data.verify(proof)
+    }
+}
+
+fn boards() -> Vec<Board> {
+    vec![
+        [
+            2, 2, 4, 0, //
+            2, 2, 4, 0, //
+            4, 0, 0, 0, //
+            0, 0, 0, 0, //
+        ],
+        [
+            2, 2, 4, 8, //
+            2, 0, 4, 4, //
+            2, 2, 2, 4, //
+            0, 2, 4, 4, //
+        ],
+        [
+            2, 2, 0, 4, //
+            0, 0, 4, 0, //
+            2, 0, 0, 2, //
+            0, 4, 0, 0, //
+        ],
+        [
+            16, 8, 4, 2, //
+            8, 8, 0, 0, //
+            0, 2, 2, 2, //
+            4, 0, 4, 8, //
+        ],
+    ]
+}
+
+#[test]
+fn valid_moves_prove() {
+    let harness = Harness::new();
+    for before in boards() {
+        for direction in Direction::ALL {
+            let witness = MoveWitness::valid(before, direction);
+            harness.prove(&witness).unwrap_or_else(|err| panic!("{witness:?} rejected: {err}"));
+        }
+    }
+}
+
+#[test]
+fn mutated_witnesses_fail_to_prove() {
+    Harness::new().assert_mutations_rejected();
+}
+
+#[test]
+fn tiles_that_are_not_powers_of_two_fail_to_prove() {
+    let harness = Harness::new();
+    let mut before = [0; 16];
+    before[0] = 1 << MAX_TILE_EXPONENT;
+    harness.prove(&MoveWitness::valid(before, Direction::Right)).unwrap();
+
+    // Each pair merges consistently in the field, e.g. 3 + 3 = 6 and (p - 1) + (p - 1) = p - 2
+    for tile in [1, 3, 6, 1 << (MAX_TILE_EXPONENT + 1), F::ORDER - 1] {
+        let mut witness = MoveWitness::valid([0; 16], Direction::Left);
+        witness.before[..2].copy_from_slice(&[tile, tile]);
+        witness.after[0] = (F::from_canonical_u64(tile) * F::TWO).to_canonical_u64();
+        assert!(harness.prove(&witness).is_err(), "{tile} + {tile} accepted");
+    }
+}
+
+#[test]
+fn lookup_valid_moves_prove() {
+    let harness = Harness::lookup();
+    for before in boards() {
+        let witness = MoveWitness::valid(before, Direction::Left);
+        harness.prove(&witness).unwrap_or_else(|err| panic!("{witness:?} rejected: {err}"));
+    }
+}
+
+#[test]
+fn lookup_mutated_witnesses_fail_to_prove() {
+    Harness::lookup().assert_mutations_rejected();
+}
+
+#[test]
+fn lookup_rejects_merge_past_max_tile() {
+    let harness = Harness::lookup();
+    let mut before = [0; 16];
+    before[0] = 1 << MAX_LOOKUP_EXPONENT;
+    before[1] = 1 << MAX_LOOKUP_EXPONENT;
+    let witness = MoveWitness::valid(before, Direction::Left);
+    assert!(harness.prove(&witness).is_err());
+
+    // Nor can the overflowing line become any other line, e.g. [0,2,0,0]
+    let mut after = [0; 16];
+    after[1] = 2;
+    assert!(harness.prove(&witness.with_after(after)).is_err());
+}
+
+#[test]
+fn lookup_proves_moves_beside_max_tiles() {
+    let harness = Harness::lookup();
+    let mut before = [0; 16];
+    before[0] = 1 << MAX_LOOKUP_EXPONENT;
+    before[1] = 1 << MAX_LOOKUP_EXPONENT;
+    let witness = MoveWitness::valid(before, Direction::Down);
+    harness.prove(&witness).unwrap();
+}
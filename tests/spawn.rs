@@ -0,0 +1,191 @@
+use game2048_plonky2::game2048::{Board, CircuitProfile, Direction, Game2048Circuit, Game2048Engine, C, D, F};
+use game2048_plonky2::{PlayerEntropy, PlayerIdentity, RuleSet, ServerSeed, ThreesRules};
+use plonky2::field::types::{Field, PrimeField64};
+use plonky2::hash::hash_types::{HashOut, HashOutTarget};
+use plonky2::iop::generator::generate_partial_witness;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, Witness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+
+/// A circuit that only runs `add_spawn`, so the spawned board can be read back from the witness
+struct SpawnCircuit {
+    data: CircuitData<F, C, D>,
+    board: [Target; 16],
+    seed: HashOutTarget,
+    spawn_index: Target,
+    /// Set when the circuit spawns after a move, through `add_spawn_with_rules`
+    direction: Option<Target>,
+    spawned: [Target; 16],
+}
+
+impl SpawnCircuit {
+    fn build() -> Self {
+        Self::build_with(|builder, board, seed, spawn_index| {
+            (Game2048Circuit::add_spawn(builder, board, seed, spawn_index), None)
+        })
+    }
+
+    /// A circuit spawning under rules after a move in a direction given as a witness
+    fn build_with_rules(rules: &dyn RuleSet) -> Self {
+        Self::build_with(|builder, board, seed, spawn_index| {
+            let direction = builder.add_virtual_target();
+            let spawned =
+                Game2048Circuit::add_spawn_with_rules(builder, rules, board, seed, spawn_index, Some(direction));
+            (spawned, Some(direction))
+        })
+    }
+
+    fn build_with(
+        add_spawn: impl FnOnce(
+            &mut CircuitBuilder<F, D>,
+            &[Target; 16],
+            HashOutTarget,
+            Target,
+        ) -> ([Target; 16], Option<Target>),
+    ) -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let board = builder.add_virtual_target_arr::<16>();
+        let seed = builder.add_virtual_hash();
+        let spawn_index = builder.add_virtual_target();
+        let (spawned, direction) = add_spawn(&mut builder, &board, seed, spawn_index);
+        let data = builder.build::<C>();
+        Self { data, board, seed, spawn_index, direction, spawned }
+    }
+
+    fn witness(&self, board: &Board, seed: &HashOut<F>, spawn_index: u64) -> PartialWitness<F> {
+        let mut pw = PartialWitness::<F>::new();
+        for (&target, &tile) in self.board.iter().zip(board) {
+            pw.set_target(target, F::from_canonical_u64(tile)).unwrap();
+        }
+        pw.set_hash_target(self.seed, *seed).unwrap();
+        pw.set_target(self.spawn_index, F::from_canonical_u64(spawn_index)).unwrap();
+        pw
+    }
+
+    fn spawn(&self, board: &Board, seed: &HashOut<F>, spawn_index: u64) -> Board {
+        self.spawn_after(board, seed, spawn_index, None)
+    }
+
+    fn spawn_after(&self, board: &Board, seed: &HashOut<F>, spawn_index: u64, direction: Option<Direction>) -> Board {
+        let mut pw = self.witness(board, seed, spawn_index);
+        if let (Some(target), Some(direction)) = (self.direction, direction) {
+            pw.set_target(target, F::from_canonical_u64(direction.as_u64())).unwrap();
+        }
+        let witness = generate_partial_witness(pw, &self.data.prover_only, &self.data.common).unwrap();
+        self.spawned.map(|target| witness.get_target(target).to_canonical_u64())
+    }
+}
+
+/// Pseudo-random boards with anywhere from 0 to 16 empty cells
+fn board(i: u64) -> Board {
+    let mut state = i.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    let empty_odds = i % 17;
+    let mut board = [0; 16];
+    for tile in board.iter_mut() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        if state % 16 >= empty_odds {
+            *tile = 2 << (state % 5);
+        }
+    }
+    board
+}
+
+fn seed(i: u64) -> HashOut<F> {
+    HashOut::from_partial(&[F::from_canonical_u64(i), F::from_canonical_u64(i * i + 7)])
+}
+
+#[test]
+fn native_and_circuit_spawns_agree_for_thousands_of_seeds() {
+    let circuit = SpawnCircuit::build();
+    let mut fours = 0;
+    for i in 0..2000 {
+        let (board, seed) = (board(i), seed(i));
+        let expected = Game2048Engine::spawn_tile(&board, &seed, i);
+        assert_eq!(circuit.spawn(&board, &seed, i), expected, "seed {i}, board {board:?}");
+        if let Some((_, 4)) = Game2048Engine::spawn_position(&board, &seed, i) {
+            fours += 1;
+        }
+    }
+    // Roughly one spawn in ten is a 4
+    assert!((100..300).contains(&fours), "{fours} fours");
+}
+
+#[test]
+fn threes_spawns_agree_with_the_circuit_in_every_direction() {
+    let circuit = SpawnCircuit::build_with_rules(&ThreesRules);
+    for i in 0..200 {
+        let (board, seed) = (board(i), seed(i));
+        for direction in Direction::ALL {
+            let expected = ThreesRules.spawn_tile(&board, &seed, i, Some(direction));
+            assert_eq!(circuit.spawn_after(&board, &seed, i, Some(direction)), expected, "seed {i}, {direction:?}");
+        }
+    }
+
+    // The spawn goes on the right edge after a move to the left, and a direction outside
+    // 0..4 fails to prove
+    let board = [0; 16];
+    let seed = seed(7);
+    let spawned = circuit.spawn_after(&board, &seed, 7, Some(Direction::Left));
+    let (cell, value) = ThreesRules.spawn_position(&board, &seed, 7, Some(Direction::Left)).unwrap();
+    assert_eq!((cell % 4, spawned[cell]), (3, value));
+
+    let mut pw = circuit.witness(&board, &seed, 7);
+    pw.set_target(circuit.direction.unwrap(), F::from_canonical_u64(Direction::Left.as_u64())).unwrap();
+    let proof = circuit.data.prove(pw).unwrap();
+    circuit.data.verify(proof).unwrap();
+    let mut pw = circuit.witness(&board, &seed, 7);
+    pw.set_target(circuit.direction.unwrap(), F::from_canonical_u64(4)).unwrap();
+    assert!(circuit.data.prove(pw).is_err());
+}
+
+#[test]
+fn spawn_witnesses_satisfy_the_constraints() {
+    let circuit = SpawnCircuit::build();
+    let full_board = [2; 16];
+    for (board, i) in [(board(1), 1), (board(16), 16), ([0; 16], 3), (full_board, 4)] {
+        let proof = circuit.data.prove(circuit.witness(&board, &seed(i), i)).unwrap();
+        circuit.data.verify(proof).unwrap();
+    }
+    assert_eq!(Game2048Engine::spawn_tile(&full_board, &seed(4), 4), full_board);
+}
+
+#[test]
+fn spawn_circuit_proves_move_and_spawn() {
+    let (builder, targets) = Game2048Circuit::build_spawn_circuit(CircuitProfile::Recursion);
+    let data = builder.build::<C>();
+    let server_seed = ServerSeed::random();
+    let player_entropy: PlayerEntropy = [5, 6, 7, 8].map(F::from_canonical_u64);
+    let identity = PlayerIdentity::new(&[F::from_canonical_u64(42)], 1);
+    let before_board = board(3);
+
+    let mut pw = PartialWitness::<F>::new();
+    let after_board = targets
+        .set_witness(&mut pw, &before_board, Direction::Left, &server_seed, &player_entropy, &identity, 9)
+        .unwrap();
+    let proof = data.prove(pw).unwrap();
+    data.verify(proof.clone()).unwrap();
+
+    let seed = Game2048Engine::combine_seed(&server_seed, &player_entropy, &identity);
+    let moved_board = Game2048Engine::apply_move(&before_board, Direction::Left);
+    assert_eq!(after_board, Game2048Engine::spawn_tile(&moved_board, &seed, 9));
+    assert_ne!(after_board, moved_board);
+    assert_eq!(HashOut::from_partial(&proof.public_inputs[34..38]), server_seed.commitment());
+
+    // Placing the tile anywhere else fails
+    let (spawn_cell, _) = Game2048Engine::spawn_position(&moved_board, &seed, 9).unwrap();
+    let other_cell = (0..16).find(|&cell| moved_board[cell] == 0 && cell != spawn_cell).unwrap();
+    let mut forged_board = moved_board;
+    forged_board[other_cell] = after_board[spawn_cell];
+
+    let mut pw = PartialWitness::<F>::new();
+    targets.game.set_witness(&mut pw, &before_board, &forged_board, Direction::Left).unwrap();
+    for (&target, &tile) in targets.moved_board.iter().zip(&moved_board) {
+        pw.set_target(target, F::from_canonical_u64(tile)).unwrap();
+    }
+    targets.seed.set_witness(&mut pw, &server_seed, &player_entropy, &identity).unwrap();
+    pw.set_target(targets.spawn_index, F::from_canonical_u64(9)).unwrap();
+    assert!(data.prove(pw).is_err());
+}
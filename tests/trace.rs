@@ -0,0 +1,323 @@
+use game2048_plonky2::game2048::{TraceTargets, C, F, IDENTITY_LEN};
+use game2048_plonky2::{
+    Board, CircuitProfile, CommittedTraceProver, Direction, Game2048Circuit, Game2048Engine, Game2048Error,
+    Game2048TraceProver, PlayerEntropy, PlayerIdentity, ServerSeed, TimedMove,
+};
+use plonky2::field::types::Field;
+use plonky2::hash::hash_types::HashOut;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+
+fn server_seed() -> ServerSeed {
+    ServerSeed([1, 2, 3, 4].map(F::from_canonical_u64))
+}
+
+fn player_entropy() -> PlayerEntropy {
+    [11, 12, 13, 14].map(F::from_canonical_u64)
+}
+
+fn identity() -> PlayerIdentity {
+    PlayerIdentity::new(&[F::from_canonical_u64(42)], 1)
+}
+
+fn seed() -> HashOut<F> {
+    Game2048Engine::combine_seed(&server_seed(), &player_entropy(), &identity())
+}
+
+const DIRECTIONS: [Direction; 3] = [Direction::Left, Direction::Up, Direction::Right];
+
+const MIN_INTERVAL: u64 = 100;
+
+fn timed_moves(timestamps: [u64; 3]) -> Vec<TimedMove> {
+    DIRECTIONS
+        .iter()
+        .zip(timestamps)
+        .map(|(&direction, timestamp)| TimedMove { direction, timestamp })
+        .collect()
+}
+
+fn moves() -> Vec<TimedMove> {
+    timed_moves([1_000, 1_100, 1_450])
+}
+
+/// Every board of the game: the opening board, then the board after each move and its spawn
+fn boards() -> Vec<Board> {
+    Game2048Engine::play(&seed(), &DIRECTIONS)
+}
+
+#[test]
+fn trace_proves_consecutive_moves_from_the_opening_board() {
+    let prover = Game2048TraceProver::new(DIRECTIONS.len(), MIN_INTERVAL, CircuitProfile::Recursion).unwrap();
+    let proof = prover.prove(&server_seed(), &player_entropy(), &identity(), &moves()).unwrap();
+    prover.verify(&proof).unwrap();
+
+    assert_eq!(proof.final_board(), boards()[DIRECTIONS.len()]);
+    assert_eq!(proof.directions(), DIRECTIONS.map(Some));
+    assert_eq!((proof.first_timestamp(), proof.last_timestamp()), (1_000, 1_450));
+    assert_eq!(proof.player_entropy(), player_entropy());
+    proof.check_server_commitment(&server_seed().commitment()).unwrap();
+    proof.check_player_identity(&identity()).unwrap();
+
+    assert!(matches!(
+        prover.prove(&server_seed(), &player_entropy(), &identity(), &moves()[..2]),
+        Err(Game2048Error::TraceLength { expected: 3, len: 2 })
+    ));
+    assert!(matches!(
+        Game2048TraceProver::new(0, MIN_INTERVAL, CircuitProfile::Recursion),
+        Err(Game2048Error::EmptyTrace)
+    ));
+    assert!(matches!(
+        Game2048Circuit::build_committed_trace_circuit(0, MIN_INTERVAL, CircuitProfile::Recursion),
+        Err(Game2048Error::EmptyTrace)
+    ));
+}
+
+#[test]
+fn trace_is_bound_to_the_player_identity() {
+    let prover = Game2048TraceProver::new(DIRECTIONS.len(), MIN_INTERVAL, CircuitProfile::Recursion).unwrap();
+    let proof = prover.prove(&server_seed(), &player_entropy(), &identity(), &moves()).unwrap();
+
+    // Claiming the proof for another session fails
+    let other_session = PlayerIdentity::new(&[F::from_canonical_u64(42)], 2);
+    assert!(matches!(proof.check_player_identity(&other_session), Err(Game2048Error::IdentityMismatch)));
+    let mut claimed = proof.clone();
+    claimed.proof.public_inputs[8..8 + IDENTITY_LEN].copy_from_slice(&other_session.elements());
+    assert!(prover.verify(&claimed).is_err());
+}
+
+#[test]
+fn trace_proves_leaderboard_fields() {
+    let prover = Game2048TraceProver::new(DIRECTIONS.len(), MIN_INTERVAL, CircuitProfile::Recursion).unwrap();
+    let proof = prover.prove(&server_seed(), &player_entropy(), &identity(), &moves()).unwrap();
+    prover.verify(&proof).unwrap();
+
+    let stats = proof.leaderboard_stats();
+    assert_eq!(stats, Game2048Engine::leaderboard_stats(&boards()[DIRECTIONS.len()], &DIRECTIONS));
+    assert_eq!((stats.num_moves, stats.direction_counts), (3, [1, 0, 1, 1]));
+
+    // Claiming a higher tile or another move count fails
+    let max_tile_index = proof.proof.public_inputs.len() - 5;
+    for (index, delta) in [(max_tile_index, 16), (max_tile_index - 1, 1), (max_tile_index + 2, 1)] {
+        let mut forged = proof.clone();
+        forged.proof.public_inputs[index] += F::from_canonical_u64(delta);
+        assert!(prover.verify(&forged).is_err());
+    }
+}
+
+/// Assign a trace's seed, initial board, moved boards and timed moves directly,
+/// bypassing the native checks
+fn forge_trace(
+    targets: &TraceTargets,
+    initial_board: &Board,
+    moved_boards: &[Board],
+    moves: &[TimedMove],
+) -> PartialWitness<F> {
+    let mut pw = PartialWitness::<F>::new();
+    targets.opening.seed.set_witness(&mut pw, &server_seed(), &player_entropy(), &identity()).unwrap();
+    pw.set_target(targets.start_move, F::ZERO).unwrap();
+    for (&target, &tile) in targets.boards[0].iter().zip(initial_board) {
+        pw.set_target(target, F::from_canonical_u64(tile)).unwrap();
+    }
+    for (board_targets, board) in targets.moved_boards.iter().zip(moved_boards) {
+        for (&target, &tile) in board_targets.iter().zip(board) {
+            pw.set_target(target, F::from_canonical_u64(tile)).unwrap();
+        }
+    }
+    for (n, timed_move) in moves.iter().enumerate() {
+        pw.set_target(targets.directions[n], F::from_canonical_u64(timed_move.direction.as_u64())).unwrap();
+        pw.set_target(targets.timestamps[n], F::from_canonical_u64(timed_move.timestamp)).unwrap();
+    }
+    pw
+}
+
+#[test]
+fn trace_rejects_boards_that_do_not_follow() {
+    let (builder, targets) =
+        Game2048Circuit::build_trace_circuit(DIRECTIONS.len(), MIN_INTERVAL, CircuitProfile::Recursion).unwrap();
+    let data = builder.build::<C>();
+
+    let boards = boards();
+    let moved_boards: Vec<_> = boards
+        .iter()
+        .zip(DIRECTIONS)
+        .map(|(board, direction)| Game2048Engine::apply_move(board, direction))
+        .collect();
+    data.verify(data.prove(forge_trace(&targets, &boards[0], &moved_boards, &moves())).unwrap()).unwrap();
+
+    // Start from a board of the prover's choosing instead of the opening board
+    let mut chosen = boards[0];
+    chosen[chosen.iter().position(|&tile| tile == 0).unwrap()] = 1024;
+    let chosen_moved: Vec<_> = Game2048Engine::play_from(&seed(), &chosen, 0, &DIRECTIONS)
+        .iter()
+        .zip(DIRECTIONS)
+        .map(|(board, direction)| Game2048Engine::apply_move(board, direction))
+        .collect();
+    assert!(data.prove(forge_trace(&targets, &chosen, &chosen_moved, &moves())).is_err());
+    let prover = CommittedTraceProver::with_profile(1, 0, CircuitProfile::Recursion).unwrap();
+    let salt = Game2048Engine::random_salt();
+    assert!(matches!(
+        prover.prove(&server_seed(), &player_entropy(), &identity(), 0, &chosen, &moves()[..1], &salt, &salt),
+        Err(Game2048Error::NotOpeningBoard)
+    ));
+
+    // Double a tile of the first moved board
+    let mut doubled = moved_boards.clone();
+    let cell = doubled[0].iter().position(|&tile| tile != 0).unwrap();
+    doubled[0][cell] *= 2;
+    assert!(data.prove(forge_trace(&targets, &boards[0], &doubled, &moves())).is_err());
+
+    // A decreasing and a too-short interval
+    for timestamps in [[1_000, 1_100, 1_050], [1_000, 1_099, 1_450]] {
+        let forged = forge_trace(&targets, &boards[0], &moved_boards, &timed_moves(timestamps));
+        assert!(data.prove(forged).is_err(), "{timestamps:?}");
+    }
+}
+
+#[test]
+fn committed_move_reveals_only_commitments() {
+    let prover = CommittedTraceProver::new();
+    let down = TimedMove { direction: Direction::Down, timestamp: 1_000 };
+    let opening = Game2048Engine::opening_board(&seed());
+    let after_board = Game2048Engine::play(&seed(), &[Direction::Down])[1];
+    let (initial_salt, final_salt) = (Game2048Engine::random_salt(), Game2048Engine::random_salt());
+
+    let proof = prover
+        .prove(&server_seed(), &player_entropy(), &identity(), 0, &opening, &[down], &initial_salt, &final_salt)
+        .unwrap();
+    prover.verify(&proof).unwrap();
+
+    assert_eq!(proof.proof.public_inputs.len(), 8 + IDENTITY_LEN + 4 + 8);
+    assert_eq!((proof.first_timestamp(), proof.last_timestamp()), (1_000, 1_000));
+    assert_eq!(proof.player_entropy(), player_entropy());
+    proof.check_server_commitment(&server_seed().commitment()).unwrap();
+    let commit = |board, salt| Game2048Engine::commit_board(board, salt, &identity(), 0);
+    assert_eq!(proof.initial_commitment(), commit(&opening, &initial_salt));
+    assert_eq!(proof.final_commitment(), Game2048Engine::commit_board(&after_board, &final_salt, &identity(), 1));
+    assert_ne!(proof.final_commitment(), commit(&after_board, &final_salt));
+}
+
+#[test]
+fn committed_trace_binds_both_boards() {
+    let prover = CommittedTraceProver::with_profile(DIRECTIONS.len(), MIN_INTERVAL, CircuitProfile::Recursion).unwrap();
+    let boards = boards();
+    let (initial_salt, final_salt) = (Game2048Engine::random_salt(), Game2048Engine::random_salt());
+
+    let proof = prover
+        .prove(&server_seed(), &player_entropy(), &identity(), 0, &boards[0], &moves(), &initial_salt, &final_salt)
+        .unwrap();
+    prover.verify(&proof).unwrap();
+    assert_eq!(proof.initial_commitment(), Game2048Engine::commit_board(&boards[0], &initial_salt, &identity(), 0));
+    assert_eq!(proof.final_commitment(), Game2048Engine::commit_board(&boards[3], &final_salt, &identity(), 3));
+    assert_eq!((proof.start_move(), proof.end_move()), (0, 3));
+
+    let mut forged = proof.clone();
+    let other_commitment = Game2048Engine::commit_board(&boards[0], &final_salt, &identity(), 3);
+    forged.proof.public_inputs[4..8].copy_from_slice(&other_commitment.elements);
+    assert!(prover.verify(&forged).is_err());
+}
+
+#[test]
+fn committed_trace_is_bound_to_the_player_identity() {
+    let prover = CommittedTraceProver::with_profile(DIRECTIONS.len(), MIN_INTERVAL, CircuitProfile::Recursion).unwrap();
+    let opening = Game2048Engine::opening_board(&seed());
+    let (initial_salt, final_salt) = (Game2048Engine::random_salt(), Game2048Engine::random_salt());
+    let proof = prover
+        .prove(&server_seed(), &player_entropy(), &identity(), 0, &opening, &moves(), &initial_salt, &final_salt)
+        .unwrap();
+    proof.check_player_identity(&identity()).unwrap();
+
+    // Same key in another session, and another key in the same session
+    let other_session = PlayerIdentity::new(&[F::from_canonical_u64(42)], 2);
+    let other_player = PlayerIdentity::new(&[F::from_canonical_u64(43)], 1);
+    for other in [other_session, other_player] {
+        assert!(matches!(proof.check_player_identity(&other), Err(Game2048Error::IdentityMismatch)));
+        assert_ne!(proof.initial_commitment(), Game2048Engine::commit_board(&opening, &initial_salt, &other, 0));
+
+        // Claiming the proof for another identity fails
+        let mut claimed = proof.clone();
+        claimed.proof.public_inputs[8..8 + IDENTITY_LEN].copy_from_slice(&other.elements());
+        assert!(prover.verify(&claimed).is_err());
+    }
+}
+
+#[test]
+fn chained_segments_reject_undo() {
+    let prover = CommittedTraceProver::with_profile(1, MIN_INTERVAL, CircuitProfile::Recursion).unwrap();
+    let boards = boards();
+    let salts: Vec<_> = boards.iter().map(|_| Game2048Engine::random_salt()).collect();
+
+    let segment_with = |server_seed: &ServerSeed, start: usize, board: &Board, timed_move| {
+        let (entropy, salts) = (player_entropy(), (&salts[start], &salts[start + 1]));
+        prover
+            .prove(server_seed, &entropy, &identity(), start as u64, board, &[timed_move], salts.0, salts.1)
+            .unwrap()
+    };
+    let segment = |start, board, timed_move| segment_with(&server_seed(), start, board, timed_move);
+    let moves = moves();
+    let segments: Vec<_> = (0..DIRECTIONS.len()).map(|n| segment(n, &boards[n], moves[n])).collect();
+    prover.verify_chain(&segments).unwrap();
+
+    // Undo the first move: the second segment starts again from the opening board
+    let undo = segment(1, &boards[0], moves[1]);
+    prover.verify(&undo).unwrap();
+    let spliced = [segments[0].clone(), undo, segments[2].clone()];
+    assert!(matches!(prover.verify_chain(&spliced), Err(Game2048Error::BrokenChain { segment: 1 })));
+
+    // Continue the game under another seed, from the committed board
+    let reseeded = segment_with(&ServerSeed::random(), 1, &boards[1], moves[1]);
+    prover.verify(&reseeded).unwrap();
+    assert_eq!(reseeded.initial_commitment(), segments[0].final_commitment());
+    let spliced = [segments[0].clone(), reseeded];
+    assert!(matches!(prover.verify_chain(&spliced), Err(Game2048Error::BrokenChain { segment: 1 })));
+
+    // Replay the first segment in place of the second: the move counter goes back
+    let replayed = [segments[0].clone(), segments[0].clone()];
+    assert!(matches!(prover.verify_chain(&replayed), Err(Game2048Error::BrokenChain { segment: 1 })));
+
+    // Skip a segment
+    let skipped = [segments[0].clone(), segments[2].clone()];
+    assert!(matches!(prover.verify_chain(&skipped), Err(Game2048Error::BrokenChain { segment: 1 })));
+
+    // The second segment's move comes too soon after, or before, the first segment's
+    let too_soon = segment(1, &boards[1], TimedMove { timestamp: 1_050, ..moves[1] });
+    assert!(matches!(
+        prover.verify_chain(&[segments[0].clone(), too_soon]),
+        Err(Game2048Error::MoveTooSoon { move_index: 1, timestamp: 1_050, previous: 1_000, .. })
+    ));
+    let earlier = segment(1, &boards[1], TimedMove { timestamp: 900, ..moves[1] });
+    assert!(matches!(
+        prover.verify_chain(&[segments[0].clone(), earlier]),
+        Err(Game2048Error::TimestampNotIncreasing { move_index: 1, timestamp: 900, previous: 1_000 })
+    ));
+}
+
+#[test]
+fn chained_segments_start_from_the_seeded_opening() {
+    let prover = CommittedTraceProver::with_profile(1, MIN_INTERVAL, CircuitProfile::Recursion).unwrap();
+    let boards = boards();
+    let moves = moves();
+    let salts: Vec<_> = boards.iter().map(|_| Game2048Engine::random_salt()).collect();
+    let segment = |start: usize, board: &Board, timed_move| {
+        let salts = (&salts[start], &salts[start + 1]);
+        prover
+            .prove(&server_seed(), &player_entropy(), &identity(), start as u64, board, &[timed_move], salts.0, salts.1)
+            .unwrap()
+    };
+
+    // Splice a board from later in the real game in as the opening: the circuit pins move 0
+    // to the board derived from the seed
+    assert!(matches!(
+        prover.prove(&server_seed(), &player_entropy(), &identity(), 0, &boards[2], &moves[..1], &salts[0], &salts[1]),
+        Err(Game2048Error::NotOpeningBoard)
+    ));
+
+    // Start the chain mid-game instead, replaying from an earlier board under a later move index
+    let replayed = Game2048Engine::play_from(&seed(), &boards[0], 1, &DIRECTIONS[1..]);
+    let mid_game = [segment(1, &replayed[0], moves[1]), segment(2, &replayed[1], moves[2])];
+    prover.verify(&mid_game[0]).unwrap();
+    assert_eq!(mid_game[1].initial_commitment(), mid_game[0].final_commitment());
+    assert!(matches!(prover.verify_chain(&mid_game), Err(Game2048Error::BrokenChain { segment: 0 })));
+
+    // The honest chain of the same game verifies
+    let honest: Vec<_> = (0..DIRECTIONS.len()).map(|n| segment(n, &boards[n], moves[n])).collect();
+    prover.verify_chain(&honest).unwrap();
+}